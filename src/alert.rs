@@ -0,0 +1,472 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::io::{Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::process::Command;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+use chrono::Local;
+use serde::Serialize;
+use nping::error_log::{ErrorKind, ErrorLog};
+use nping::health::{Health, Thresholds};
+use nping::ip_data::IpData;
+use nping::status::Status;
+use nping::stats::calculate_loss_pkg;
+
+/// give up delivering a webhook after this long
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum AlertEvent {
+    /// a threshold was crossed to warning
+    Warn,
+    /// a threshold was crossed to critical
+    Crit,
+    /// back below all thresholds
+    Ok,
+    Down,
+    Up,
+}
+
+impl fmt::Display for AlertEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            AlertEvent::Warn => "warn",
+            AlertEvent::Crit => "crit",
+            AlertEvent::Ok => "ok",
+            AlertEvent::Down => "down",
+            AlertEvent::Up => "up",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// a change of a target worth notifying about, also the body of the webhook
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct Alert {
+    pub(crate) event: AlertEvent,
+    pub(crate) target: String,
+    pub(crate) label: Option<String>,
+    pub(crate) group: Option<String>,
+    pub(crate) ip: String,
+    /// loss in percent since the start
+    pub(crate) loss: f64,
+    /// rtts in milliseconds, the average is since the start and the last one is -1.0 on timeout
+    pub(crate) avg_rtt: f64,
+    pub(crate) last_rtt: f64,
+    /// rfc 3339 time of the alert
+    pub(crate) time: String,
+}
+
+impl Alert {
+    fn new(event: AlertEvent, data: &IpData) -> Self {
+        Self {
+            event,
+            target: data.addr.clone(),
+            label: data.label.clone(),
+            group: data.group.clone(),
+            ip: data.ip.clone(),
+            loss: calculate_loss_pkg(data.timeout, data.received),
+            avg_rtt: data.session_avg_rtt(),
+            last_rtt: data.last_attr,
+            time: Local::now().to_rfc3339(),
+        }
+    }
+
+    // the variables the alert command gets
+    fn env(&self) -> Vec<(&'static str, String)> {
+        vec![
+            ("NPING_EVENT", self.event.to_string()),
+            ("NPING_TARGET", self.target.clone()),
+            ("NPING_LABEL", self.label.clone().unwrap_or_default()),
+            ("NPING_GROUP", self.group.clone().unwrap_or_default()),
+            ("NPING_IP", self.ip.clone()),
+            ("NPING_LOSS", format!("{:.2}", self.loss)),
+            ("NPING_AVG_RTT", format!("{:.2}", self.avg_rtt)),
+            ("NPING_LAST_RTT", format!("{:.2}", self.last_rtt)),
+            ("NPING_TIME", self.time.clone()),
+        ]
+    }
+}
+
+/// where alerts are delivered to
+#[derive(Debug, Clone, Default)]
+pub(crate) struct AlertOptions {
+    /// shell command run for every alert
+    pub(crate) command: Option<String>,
+    /// ring the terminal bell
+    pub(crate) bell: bool,
+    /// http url the alert is posted to as json
+    pub(crate) webhook: Option<String>,
+}
+
+impl AlertOptions {
+    pub(crate) fn is_enabled(&self) -> bool {
+        self.command.is_some() || self.bell || self.webhook.is_some()
+    }
+}
+
+// the state of a target alerts are sent for when it changes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+struct TargetState {
+    health: Health,
+    down: bool,
+}
+
+/// follows the health of the targets and delivers an alert when it changes
+#[derive(Debug)]
+pub(crate) struct Alerter {
+    options: AlertOptions,
+    errs: Arc<Mutex<ErrorLog>>,
+    states: HashMap<usize, TargetState>,
+}
+
+impl Alerter {
+    pub(crate) fn new(options: AlertOptions, errs: Arc<Mutex<ErrorLog>>) -> Self {
+        Self {
+            options,
+            errs,
+            states: HashMap::new(),
+        }
+    }
+
+    /// compare the targets with their last state and deliver the alerts of the changes
+    pub(crate) fn check(&mut self, ip_data: &[IpData], thresholds: &Thresholds) {
+        if !self.options.is_enabled() {
+            return;
+        }
+        self.states.retain(|id, _| ip_data.iter().any(|data| data.id == *id));
+        for data in ip_data.iter().filter(|data| !data.rtts.is_empty()) {
+            let state = TargetState {
+                health: thresholds.for_target(&data.options).target(data),
+                down: data.status.status == Status::Down,
+            };
+            let last = self.states.insert(data.id, state).unwrap_or_default();
+            for event in transitions(last, state) {
+                self.deliver(Alert::new(event, data));
+            }
+        }
+    }
+
+    fn deliver(&self, alert: Alert) {
+        if self.options.bell {
+            // the bell goes straight to the terminal, next to the ui
+            let mut stdout = std::io::stdout();
+            let _ = stdout.write_all(b"\x07").and_then(|_| stdout.flush());
+        }
+        if let Some(command) = self.options.command.clone() {
+            let errs = self.errs.clone();
+            let alert = alert.clone();
+            thread::spawn(move || {
+                let result = Command::new("sh").arg("-c").arg(&command).envs(alert.env()).output();
+                let err = match result {
+                    Ok(output) if output.status.success() => return,
+                    Ok(output) => match String::from_utf8_lossy(&output.stderr).trim() {
+                        "" => format!("alert command failed with {}", output.status),
+                        stderr => format!("alert command failed with {}: {}", output.status, stderr),
+                    },
+                    Err(err) => format!("failed to run alert command: {}", err),
+                };
+                errs.lock().unwrap().push(None, &alert.target, ErrorKind::Alert, err);
+            });
+        }
+        if let Some(url) = self.options.webhook.clone() {
+            let errs = self.errs.clone();
+            thread::spawn(move || {
+                if let Err(err) = post_webhook(&url, &alert) {
+                    errs.lock().unwrap().push(None, &alert.target, ErrorKind::Alert, format!("webhook {}: {}", url, err));
+                }
+            });
+        }
+    }
+}
+
+// the events of going from one state to the next, going down hides the health change
+fn transitions(last: TargetState, state: TargetState) -> Vec<AlertEvent> {
+    let mut events = Vec::new();
+    match (last.down, state.down) {
+        (false, true) => return vec![AlertEvent::Down],
+        (true, false) => events.push(AlertEvent::Up),
+        (true, true) => return events,
+        (false, false) => {}
+    }
+    if state.health != last.health {
+        events.push(match state.health {
+            Health::Ok => AlertEvent::Ok,
+            Health::Warn => AlertEvent::Warn,
+            Health::Crit => AlertEvent::Crit,
+        });
+    }
+    events
+}
+
+/// check that a webhook url can be posted to, only plain http is supported
+pub(crate) fn parse_webhook(url: &str) -> Result<String, String> {
+    match url.strip_prefix("http://") {
+        Some(rest) if !rest.is_empty() => Ok(url.to_string()),
+        _ => Err(format!("webhook url must start with http://, got: {}", url)),
+    }
+}
+
+// post the alert as json with a plain http/1.1 request
+fn post_webhook(url: &str, alert: &Alert) -> Result<(), String> {
+    let rest = url.strip_prefix("http://").ok_or("only http:// is supported")?;
+    let (authority, path) = match rest.find('/') {
+        Some(index) => rest.split_at(index),
+        None => (rest, "/"),
+    };
+    let address = if authority.contains(':') && !authority.ends_with(']') {
+        authority.to_string()
+    } else {
+        format!("{}:80", authority)
+    };
+    let address = address
+        .to_socket_addrs()
+        .map_err(|err| err.to_string())?
+        .next()
+        .ok_or("could not resolve the webhook host")?;
+
+    let body = serde_json::to_string(alert).map_err(|err| err.to_string())?;
+    let request = format!(
+        "POST {} HTTP/1.1\r\nHost: {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        path,
+        authority,
+        body.len(),
+        body
+    );
+
+    let mut stream = TcpStream::connect_timeout(&address, WEBHOOK_TIMEOUT).map_err(|err| err.to_string())?;
+    stream.set_read_timeout(Some(WEBHOOK_TIMEOUT)).map_err(|err| err.to_string())?;
+    stream.write_all(request.as_bytes()).map_err(|err| err.to_string())?;
+
+    let mut response = String::new();
+    let _ = stream.read_to_string(&mut response);
+    let status = response.split_whitespace().nth(1).unwrap_or_default();
+    if status.starts_with('2') {
+        Ok(())
+    } else {
+        Err(format!("unexpected response: {}", response.lines().next().unwrap_or("none")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+    use nping::ip_data::apply_event;
+    use nping::network::{AddressPick, IpFamily, PingContext, PingTaskManager, ResolveOptions};
+    use nping::pinger::MockProber;
+    use nping::status::EventLog;
+    use nping::target::TargetSpec;
+    use std::thread::JoinHandle;
+    use std::time::Instant;
+
+    fn state(health: Health, down: bool) -> TargetState {
+        TargetState { health, down }
+    }
+
+    // a target with `replies` rtts of 10ms, 20ms, ... and `timeouts` timeouts after them
+    fn target(replies: usize, timeouts: usize) -> IpData {
+        let mut data = IpData::new(0, "example.com".to_string(), "10.0.0.1".to_string());
+        data.label = Some("web".to_string());
+        for reply in 1..=replies {
+            data.update_rtt(reply as f64 * 10.0, 2, Local::now());
+        }
+        for _ in 0..timeouts {
+            data.update_timeout(2, Local::now());
+        }
+        data
+    }
+
+    fn alert(event: AlertEvent) -> Alert {
+        Alert::new(event, &target(3, 1))
+    }
+
+    // answer one request with `response` and hand back the request
+    fn serve(response: &'static str) -> (String, JoinHandle<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let server = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = Vec::new();
+            let mut buf = [0; 1024];
+            loop {
+                let read = stream.read(&mut buf).unwrap();
+                request.extend_from_slice(&buf[..read]);
+                let text = String::from_utf8_lossy(&request).to_string();
+                if let Some((head, body)) = text.split_once("\r\n\r\n") {
+                    let length = head
+                        .lines()
+                        .find_map(|line| line.strip_prefix("Content-Length: "))
+                        .map_or(0, |length| length.parse().unwrap());
+                    if body.len() >= length || read == 0 {
+                        stream.write_all(response.as_bytes()).unwrap();
+                        return text;
+                    }
+                }
+            }
+        });
+        (url, server)
+    }
+
+    #[test]
+    fn health_changes_are_alerts() {
+        assert_eq!(transitions(state(Health::Ok, false), state(Health::Warn, false)), [AlertEvent::Warn]);
+        assert_eq!(transitions(state(Health::Warn, false), state(Health::Crit, false)), [AlertEvent::Crit]);
+        assert_eq!(transitions(state(Health::Crit, false), state(Health::Ok, false)), [AlertEvent::Ok]);
+        assert!(transitions(state(Health::Warn, false), state(Health::Warn, false)).is_empty());
+    }
+
+    #[test]
+    fn going_down_hides_the_health_change() {
+        assert_eq!(transitions(state(Health::Ok, false), state(Health::Crit, true)), [AlertEvent::Down]);
+        assert!(transitions(state(Health::Crit, true), state(Health::Ok, true)).is_empty());
+        assert_eq!(transitions(state(Health::Crit, true), state(Health::Crit, false)), [AlertEvent::Up]);
+        assert_eq!(transitions(state(Health::Crit, true), state(Health::Warn, false)), [AlertEvent::Up, AlertEvent::Warn]);
+    }
+
+    #[test]
+    fn alerts_cover_the_whole_session() {
+        // only the last two rtts are kept, 30ms and a timeout
+        let alert = alert(AlertEvent::Warn);
+        assert_eq!(alert.avg_rtt, 20.0);
+        assert_eq!(alert.loss, 25.0);
+        assert_eq!(alert.last_rtt, -1.0);
+    }
+
+    #[test]
+    fn alert_command_gets_the_alert_in_variables() {
+        let env: HashMap<&str, String> = alert(AlertEvent::Down).env().into_iter().collect();
+        assert_eq!(env["NPING_EVENT"], "down");
+        assert_eq!(env["NPING_TARGET"], "example.com");
+        assert_eq!(env["NPING_LABEL"], "web");
+        assert_eq!(env["NPING_GROUP"], "");
+        assert_eq!(env["NPING_IP"], "10.0.0.1");
+        assert_eq!(env["NPING_LOSS"], "25.00");
+        assert_eq!(env["NPING_AVG_RTT"], "20.00");
+        assert_eq!(env["NPING_LAST_RTT"], "-1.00");
+
+        let path = std::env::temp_dir().join(format!("nping-{}-alert", std::process::id()));
+        let options = AlertOptions {
+            command: Some(format!("echo \"$NPING_EVENT $NPING_TARGET $NPING_LOSS\" > {}", path.display())),
+            ..AlertOptions::default()
+        };
+        let errs = Arc::new(Mutex::new(ErrorLog::default()));
+        Alerter::new(options, errs.clone()).check(&[target(3, 1)], &Thresholds::default());
+
+        let started = Instant::now();
+        let output = loop {
+            match std::fs::read_to_string(&path) {
+                Ok(output) if output.ends_with('\n') => break output,
+                _ if started.elapsed() > Duration::from_secs(5) => panic!("the alert command did not run: {:?}", errs.lock().unwrap().recent().collect::<Vec<_>>()),
+                _ => thread::sleep(Duration::from_millis(10)),
+            }
+        };
+        std::fs::remove_file(path).unwrap();
+        assert_eq!(output, "warn example.com 25.00\n");
+    }
+
+    #[test]
+    fn an_outage_alerts_down_and_then_up() {
+        let path = std::env::temp_dir().join(format!("nping-{}-outage", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let options = AlertOptions {
+            command: Some(format!("echo \"$NPING_EVENT\" >> {}", path.display())),
+            ..AlertOptions::default()
+        };
+        let errs = Arc::new(Mutex::new(ErrorLog::default()));
+        let mut alerter = Alerter::new(options, errs.clone());
+
+        // a target that replies, drops out for longer than the probe stream stops after and comes back
+        let replies: Vec<_> = [Some(Duration::from_millis(5)); 2]
+            .into_iter()
+            .chain([None; 12])
+            .chain([Some(Duration::from_millis(5)); 3])
+            .collect();
+        let (tx, rx) = std::sync::mpsc::sync_channel(0);
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let ctx = PingContext {
+            count: replies.len(),
+            interval: 1,
+            running: Arc::new(Mutex::new(true)),
+            paused: Arc::new(Mutex::new(false)),
+            resolve: ResolveOptions {
+                family: IpFamily::V4,
+                dual_stack: false,
+                timeout: Duration::from_secs(1),
+                dns_server: None,
+                pick: AddressPick::First,
+            },
+            resolve_every: None,
+            errs: errs.clone(),
+            ping_update_tx: Arc::new(tx),
+        };
+        let mut tasks = PingTaskManager::new(runtime.handle().clone(), ctx);
+        let prober = MockProber::new("10.0.0.1", replies.clone());
+        tasks.spawn_with(0, &TargetSpec::new("10.0.0.1"), "10.0.0.1".to_string(), Box::new(move |_| Ok(Box::new(prober.clone()))));
+
+        // only the outage is alerted, not the loss it leaves behind
+        let thresholds = Thresholds { warn_loss: 100.0, crit_loss: 100.0, ..Thresholds::default() };
+        let mut ip_data = vec![IpData::new(0, "10.0.0.1".to_string(), "10.0.0.1".to_string())];
+        let mut events = EventLog::default();
+        for _ in &replies {
+            let event = rx.recv_timeout(Duration::from_secs(5)).unwrap();
+            apply_event(&mut ip_data, event, 10, &thresholds, &mut events, Local::now());
+            alerter.check(&ip_data, &thresholds);
+        }
+
+        let started = Instant::now();
+        let output = loop {
+            match std::fs::read_to_string(&path) {
+                Ok(output) if output.lines().count() >= 2 => break output,
+                _ if started.elapsed() > Duration::from_secs(5) => panic!("no down and up alerts: {:?}", errs.lock().unwrap().recent().collect::<Vec<_>>()),
+                _ => thread::sleep(Duration::from_millis(10)),
+            }
+        };
+        std::fs::remove_file(path).unwrap();
+        // the commands run in the background, the order they finish in is not fixed
+        let mut alerts: Vec<&str> = output.lines().collect();
+        alerts.sort();
+        assert_eq!(alerts, ["down", "up"]);
+    }
+
+    #[test]
+    fn webhook_posts_the_alert_as_json() {
+        let (url, server) = serve("HTTP/1.1 204 No Content\r\n\r\n");
+        post_webhook(&format!("{}/alerts", url), &alert(AlertEvent::Crit)).unwrap();
+        let request = server.join().unwrap();
+
+        let (head, body) = request.split_once("\r\n\r\n").unwrap();
+        assert!(head.starts_with("POST /alerts HTTP/1.1\r\n"), "{}", head);
+        assert!(head.contains(&format!("Host: {}", url.trim_start_matches("http://"))), "{}", head);
+        assert!(head.contains("Content-Type: application/json"), "{}", head);
+        let body: serde_json::Value = serde_json::from_str(body).unwrap();
+        assert_eq!(body["event"], "crit");
+        assert_eq!(body["target"], "example.com");
+        assert_eq!(body["avg_rtt"], 20.0);
+    }
+
+    #[test]
+    fn webhook_without_a_path_posts_to_the_root() {
+        let (url, server) = serve("HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n");
+        post_webhook(&url, &alert(AlertEvent::Up)).unwrap();
+        assert!(server.join().unwrap().starts_with("POST / HTTP/1.1\r\n"));
+    }
+
+    #[test]
+    fn webhook_fails_on_other_responses() {
+        let (url, server) = serve("HTTP/1.1 500 Internal Server Error\r\nContent-Length: 0\r\n\r\n");
+        let err = post_webhook(&url, &alert(AlertEvent::Ok)).unwrap_err();
+        server.join().unwrap();
+        assert_eq!(err, "unexpected response: HTTP/1.1 500 Internal Server Error");
+    }
+
+    #[test]
+    fn webhook_urls_are_plain_http() {
+        assert_eq!(parse_webhook("http://127.0.0.1:8080/alerts").unwrap(), "http://127.0.0.1:8080/alerts");
+        assert!(parse_webhook("https://example.com").is_err());
+        assert!(parse_webhook("http://").is_err());
+    }
+}
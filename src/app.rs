@@ -0,0 +1,279 @@
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+use ratatui::crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use chrono::{DateTime, Local};
+use nping::health::Thresholds;
+use crate::replay::ReplayProgress;
+use nping::ip_data::IpData;
+use crate::ui::{sorted_indices, SortColumn};
+
+/// what the ui thread has to do after a key press
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum KeyAction {
+    None,
+    Quit,
+    /// start pinging a new host
+    AddTarget(String),
+    /// stop pinging the target with this id
+    RemoveTarget(usize),
+}
+
+/// a line of the table or graph view
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum ViewRow {
+    /// header of a group, with the indices of all its members
+    Group { name: Option<String>, members: Vec<usize>, collapsed: bool },
+    /// a target, by index into the target list
+    Target(usize),
+}
+
+/// interactive state of the ui, changed by key presses
+#[derive(Debug, Clone, Default)]
+pub(crate) struct AppState {
+    pub(crate) view_type: String,
+    pub(crate) sort_column: SortColumn,
+    pub(crate) sort_desc: bool,
+    /// index into the target list of the selected target
+    pub(crate) selected: Option<usize>,
+    /// shared with the ping tasks, no probes are sent while paused
+    pub(crate) paused: Arc<Mutex<bool>>,
+    /// show the detail pane of the selected target
+    pub(crate) show_detail: bool,
+    /// show the error log pane
+    pub(crate) show_errors: bool,
+    /// show the event log pane
+    pub(crate) show_events: bool,
+    /// text typed into the add target prompt, while it is open
+    pub(crate) input: Option<String>,
+    /// groups whose members are hidden behind their header
+    pub(crate) collapsed: HashSet<Option<String>>,
+    /// only show the members of this group
+    pub(crate) group_filter: Option<String>,
+    /// decide the colors of latency and loss
+    pub(crate) thresholds: Thresholds,
+    /// where the replay is, while a recording is shown
+    pub(crate) replay: Option<ReplayProgress>,
+}
+
+impl AppState {
+    pub(crate) fn new(view_type: String, paused: Arc<Mutex<bool>>, thresholds: Thresholds) -> Self {
+        Self {
+            view_type,
+            paused,
+            thresholds,
+            ..Default::default()
+        }
+    }
+
+    pub(crate) fn is_paused(&self) -> bool {
+        *self.paused.lock().unwrap()
+    }
+
+    /// the current time, or the recorded time while replaying
+    pub(crate) fn now(&self) -> DateTime<Local> {
+        match &self.replay {
+            Some(replay) => replay.time(),
+            None => Local::now(),
+        }
+    }
+
+    /// handle a key press
+    pub(crate) fn handle_key(&mut self, key: KeyEvent, ip_data: &mut [IpData]) -> KeyAction {
+        if key.code == KeyCode::Char('c') && key.modifiers == KeyModifiers::CONTROL {
+            return KeyAction::Quit;
+        }
+        if let Some(input) = self.input.as_mut() {
+            // typing into the add target prompt
+            match key.code {
+                KeyCode::Char(c) => input.push(c),
+                KeyCode::Backspace => {
+                    input.pop();
+                }
+                KeyCode::Enter => {
+                    let host = input.trim().to_string();
+                    self.input = None;
+                    if !host.is_empty() {
+                        return KeyAction::AddTarget(host);
+                    }
+                }
+                KeyCode::Esc => self.input = None,
+                _ => {}
+            }
+            return KeyAction::None;
+        }
+
+        match key.code {
+            // esc closes the error log and the detail pane first
+            KeyCode::Esc if self.show_errors => self.show_errors = false,
+            KeyCode::Esc if self.show_events => self.show_events = false,
+            KeyCode::Esc if self.show_detail => self.show_detail = false,
+            // set q and esc to exit
+            KeyCode::Char('q') | KeyCode::Esc => return KeyAction::Quit,
+            // cycle the sort column of the table view
+            KeyCode::Char('s') => self.sort_column = self.sort_column.next(),
+            // reverse the sort direction of the table view
+            KeyCode::Char('S') => self.sort_desc = !self.sort_desc,
+            // move the selection in display order
+            KeyCode::Up | KeyCode::Char('k') => self.move_selection(ip_data, -1),
+            KeyCode::Down | KeyCode::Char('j') => self.move_selection(ip_data, 1),
+            // open or close the detail pane of the selected target
+            KeyCode::Enter => self.show_detail = !self.show_detail && self.selected.is_some(),
+            // open or close the error log pane
+            KeyCode::Char('e') => self.show_errors = !self.show_errors,
+            // open or close the event log pane
+            KeyCode::Char('l') => self.show_events = !self.show_events,
+            // pause or resume probing
+            KeyCode::Char('p') | KeyCode::Char(' ') => {
+                let mut paused = self.paused.lock().unwrap();
+                *paused = !*paused;
+            }
+            // reset the selected target, or all targets when nothing is selected
+            KeyCode::Char('r') => match self.selected.and_then(|i| ip_data.get_mut(i)) {
+                Some(data) => data.reset(),
+                None => ip_data.iter_mut().for_each(IpData::reset),
+            },
+            // reset all targets
+            KeyCode::Char('R') => ip_data.iter_mut().for_each(IpData::reset),
+            // open the add target prompt
+            KeyCode::Char('a') => self.input = Some(String::new()),
+            // collapse or expand the group of the selected target
+            KeyCode::Char('c') => {
+                if let Some(data) = self.selected.and_then(|i| ip_data.get(i)) {
+                    if !self.collapsed.remove(&data.group) {
+                        self.collapsed.insert(data.group.clone());
+                    }
+                }
+            }
+            // collapse all groups, or expand them all when some are collapsed
+            KeyCode::Char('C') => {
+                if self.collapsed.is_empty() {
+                    self.collapsed = ip_data.iter().map(|data| data.group.clone()).collect();
+                } else {
+                    self.collapsed.clear();
+                }
+            }
+            // show only the next group, then all of them again
+            KeyCode::Char('g') => self.group_filter = next_group(ip_data, self.group_filter.as_deref()),
+            // remove the selected target
+            KeyCode::Char('d') => {
+                if let Some(data) = self.selected.and_then(|i| ip_data.get(i)) {
+                    return KeyAction::RemoveTarget(data.id);
+                }
+            }
+            _ => {}
+        }
+        KeyAction::None
+    }
+
+    /// keep the selection valid after the target at `index` was removed
+    pub(crate) fn target_removed(&mut self, index: usize, len: usize) {
+        self.selected = match self.selected {
+            _ if len == 0 => None,
+            Some(selected) if selected > index => Some(selected - 1),
+            Some(selected) => Some(selected.min(len - 1)),
+            None => None,
+        };
+        if self.selected.is_none() {
+            self.show_detail = false;
+        }
+    }
+
+    /// the target shown in the detail pane, if it is open
+    pub(crate) fn detail_target<'a>(&self, ip_data: &'a [IpData]) -> Option<&'a IpData> {
+        if self.show_detail {
+            self.selected.and_then(|i| ip_data.get(i))
+        } else {
+            None
+        }
+    }
+
+    /// the order the targets are displayed in by the current view
+    pub(crate) fn display_order(&self, ip_data: &[IpData]) -> Vec<usize> {
+        if self.view_type == "point" {
+            return self.target_order(ip_data);
+        }
+        // a collapsed group is selected through its first member
+        self.view_rows(ip_data)
+            .into_iter()
+            .filter_map(|row| match row {
+                ViewRow::Group { members, collapsed: true, .. } => members.first().copied(),
+                ViewRow::Group { .. } => None,
+                ViewRow::Target(index) => Some(index),
+            })
+            .collect()
+    }
+
+    // the targets in display order before grouping, without the ones filtered out
+    fn target_order(&self, ip_data: &[IpData]) -> Vec<usize> {
+        let order = if self.view_type == "table" {
+            sorted_indices(ip_data, self.sort_column, self.sort_desc)
+        } else {
+            (0..ip_data.len()).collect()
+        };
+        match &self.group_filter {
+            Some(group) => order.into_iter().filter(|&i| ip_data[i].group.as_ref() == Some(group)).collect(),
+            None => order,
+        }
+    }
+
+    /// the lines of the table and graph view, targets are put under a header
+    /// per group as soon as one target has a group, ungrouped ones last
+    pub(crate) fn view_rows(&self, ip_data: &[IpData]) -> Vec<ViewRow> {
+        let order = self.target_order(ip_data);
+        if ip_data.iter().all(|data| data.group.is_none()) {
+            return order.into_iter().map(ViewRow::Target).collect();
+        }
+
+        // groups are placed where their best member ranks
+        let mut groups: Vec<Option<String>> = Vec::new();
+        for &index in &order {
+            if !groups.contains(&ip_data[index].group) {
+                groups.push(ip_data[index].group.clone());
+            }
+        }
+        if let Some(pos) = groups.iter().position(Option::is_none) {
+            let ungrouped = groups.remove(pos);
+            groups.push(ungrouped);
+        }
+
+        let mut rows = Vec::new();
+        for group in groups {
+            let collapsed = self.collapsed.contains(&group);
+            let members: Vec<usize> = order.iter().copied().filter(|&i| ip_data[i].group == group).collect();
+            rows.push(ViewRow::Group { name: group, members: members.clone(), collapsed });
+            if !collapsed {
+                rows.extend(members.into_iter().map(ViewRow::Target));
+            }
+        }
+        rows
+    }
+
+    fn move_selection(&mut self, ip_data: &[IpData], step: isize) {
+        let order = self.display_order(ip_data);
+        if order.is_empty() {
+            self.selected = None;
+            return;
+        }
+        let pos = match self.selected.and_then(|i| order.iter().position(|&o| o == i)) {
+            Some(pos) => (pos as isize + step).rem_euclid(order.len() as isize) as usize,
+            None if step > 0 => 0,
+            None => order.len() - 1,
+        };
+        self.selected = Some(order[pos]);
+    }
+}
+
+// the group after `current` in order of appearance, None after the last one
+fn next_group(ip_data: &[IpData], current: Option<&str>) -> Option<String> {
+    let mut groups: Vec<&str> = Vec::new();
+    for group in ip_data.iter().filter_map(|data| data.group.as_deref()) {
+        if !groups.contains(&group) {
+            groups.push(group);
+        }
+    }
+    let next = match current.and_then(|current| groups.iter().position(|&group| group == current)) {
+        Some(pos) => groups.get(pos + 1),
+        None => groups.first(),
+    };
+    next.map(|group| group.to_string())
+}
@@ -0,0 +1,428 @@
+use std::path::{Path, PathBuf};
+use clap::parser::ValueSource;
+use clap::ArgMatches;
+use serde::{Deserialize, Deserializer};
+use nping::{network, target};
+use crate::alert;
+use crate::{Args, Commands, GlobalArgs, PingArgs};
+
+/// the config file read when --config is not given, it is fine for it to be missing
+pub(crate) fn default_config_path() -> Option<PathBuf> {
+    let config_dir = match std::env::var_os("XDG_CONFIG_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(std::env::var_os("HOME")?).join(".config"),
+    };
+    Some(config_dir.join("nping").join("config.toml"))
+}
+
+/// fill in the options not given on the command line from the config file and the profile,
+/// `matches` are the command line `args` were parsed from
+pub(crate) fn apply_config(path: &Path, args: &mut Args, matches: &ArgMatches) -> Result<(), String> {
+    let content = std::fs::read_to_string(path)
+        .map_err(|err| format!("failed to read config file {}: {}", path.display(), err))?;
+    Settings::parse(&content, args.global.profile.as_deref())
+        .and_then(|settings| settings.apply(args, matches))
+        .map_err(|err| format!("{}: {}", path.display(), err))
+}
+
+/// the options of a config file or one of its profiles.
+///
+/// keys are the names of the command line options with `_` (`view_type = "table"`,
+/// `count = 10`, `dual_stack = true`), `target` is a list of targets and
+/// `[profile.<name>]` tables bundle the same keys:
+///
+/// ```toml
+/// view_type = "table"
+///
+/// [profile.wan]
+/// target = ["1.1.1.1", "8.8.8.8"]
+/// interval = 2
+/// ```
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct Settings {
+    target: Option<Vec<String>>,
+    target_file: Option<String>,
+    count: Option<usize>,
+    interval: Option<i32>,
+    force_ipv4: Option<bool>,
+    force_ipv6: Option<bool>,
+    dual_stack: Option<bool>,
+    multiple: Option<i32>,
+    #[serde(default, deserialize_with = "text")]
+    resolve_every: Option<String>,
+    group: Option<String>,
+    watch: Option<bool>,
+    dns_server: Option<String>,
+    #[serde(default, deserialize_with = "text")]
+    pick_address: Option<String>,
+    #[serde(default, deserialize_with = "text")]
+    resolve_timeout: Option<String>,
+    on_alert: Option<String>,
+    bell: Option<bool>,
+    webhook: Option<String>,
+    event_log: Option<PathBuf>,
+    record: Option<PathBuf>,
+    probe: Option<String>,
+    port: Option<u16>,
+    view_type: Option<String>,
+    #[serde(default, deserialize_with = "text")]
+    warn_rtt: Option<String>,
+    #[serde(default, deserialize_with = "text")]
+    crit_rtt: Option<String>,
+    #[serde(default, deserialize_with = "text")]
+    warn_loss: Option<String>,
+    #[serde(default, deserialize_with = "text")]
+    crit_loss: Option<String>,
+    #[serde(default, deserialize_with = "text")]
+    down_after: Option<String>,
+    #[serde(default, deserialize_with = "text")]
+    up_after: Option<String>,
+}
+
+impl Settings {
+    /// the settings of a config file with the ones of the profile on top
+    pub(crate) fn parse(content: &str, profile: Option<&str>) -> Result<Self, String> {
+        let mut config: toml::Table = toml::from_str(content).map_err(|err| err.to_string())?;
+        let mut profiles = match config.remove("profile") {
+            Some(toml::Value::Table(profiles)) => profiles,
+            Some(_) => return Err("profile must be a table of profiles".to_string()),
+            None => toml::Table::new(),
+        };
+        let settings = Self::deserialize(toml::Value::Table(config)).map_err(|err| err.to_string())?;
+
+        let Some(name) = profile else {
+            return Ok(settings);
+        };
+        let profile = match profiles.remove(name) {
+            Some(profile) => Self::deserialize(profile).map_err(|err| format!("profile {}: {}", name, err))?,
+            None => {
+                let names: Vec<&String> = profiles.keys().collect();
+                return Err(format!("no profile named {}, available: {:?}", name, names));
+            }
+        };
+        Ok(profile.or(settings))
+    }
+
+    // the settings of self, the ones of `base` where self has none
+    fn or(self, base: Settings) -> Settings {
+        // the address family is one choice, a profile with dual_stack does not keep force_ipv6 of the file
+        let family = self.force_ipv4.is_some() || self.force_ipv6.is_some() || self.dual_stack.is_some();
+        let (force_ipv4, force_ipv6, dual_stack) = if family {
+            (self.force_ipv4, self.force_ipv6, self.dual_stack)
+        } else {
+            (base.force_ipv4, base.force_ipv6, base.dual_stack)
+        };
+        // so is the probe, the port of the file does not fit the probe of a profile
+        let (probe, port) = if self.probe.is_some() || self.port.is_some() {
+            (self.probe, self.port)
+        } else {
+            (base.probe, base.port)
+        };
+        Settings {
+            target: self.target.or(base.target),
+            target_file: self.target_file.or(base.target_file),
+            count: self.count.or(base.count),
+            interval: self.interval.or(base.interval),
+            force_ipv4,
+            force_ipv6,
+            dual_stack,
+            multiple: self.multiple.or(base.multiple),
+            resolve_every: self.resolve_every.or(base.resolve_every),
+            group: self.group.or(base.group),
+            watch: self.watch.or(base.watch),
+            dns_server: self.dns_server.or(base.dns_server),
+            pick_address: self.pick_address.or(base.pick_address),
+            resolve_timeout: self.resolve_timeout.or(base.resolve_timeout),
+            on_alert: self.on_alert.or(base.on_alert),
+            bell: self.bell.or(base.bell),
+            webhook: self.webhook.or(base.webhook),
+            event_log: self.event_log.or(base.event_log),
+            record: self.record.or(base.record),
+            probe,
+            port,
+            view_type: self.view_type.or(base.view_type),
+            warn_rtt: self.warn_rtt.or(base.warn_rtt),
+            crit_rtt: self.crit_rtt.or(base.crit_rtt),
+            warn_loss: self.warn_loss.or(base.warn_loss),
+            crit_loss: self.crit_loss.or(base.crit_loss),
+            down_after: self.down_after.or(base.down_after),
+            up_after: self.up_after.or(base.up_after),
+        }
+    }
+
+    /// set the options of `args` that were not given on the command line, a command only gets
+    /// the options it takes
+    pub(crate) fn apply(self, args: &mut Args, matches: &ArgMatches) -> Result<(), String> {
+        let command_matches = matches.subcommand().map_or(matches, |(_, matches)| matches);
+        // global options are accepted before and after the command
+        self.apply_global(&mut args.global, |id| given(matches, id) || given(command_matches, id))?;
+
+        match &mut args.command {
+            None => self.apply_ping(&mut args.ping, |id| given(matches, id)),
+            Some(Commands::Ping(ping)) => self.apply_ping(ping, |id| given(command_matches, id)),
+            // replay, report and compare only take the global options
+            Some(_) => Ok(()),
+        }
+    }
+
+    fn apply_global(&self, global: &mut GlobalArgs, given: impl Fn(&str) -> bool) -> Result<(), String> {
+        if !given("view_type") {
+            set(&mut global.view_type, self.view_type.clone());
+        }
+        if !given("warn_rtt") {
+            set_some(&mut global.warn_rtt, parse("warn_rtt", &self.warn_rtt, target::parse_rtt)?);
+        }
+        if !given("crit_rtt") {
+            set_some(&mut global.crit_rtt, parse("crit_rtt", &self.crit_rtt, target::parse_rtt)?);
+        }
+        if !given("warn_loss") {
+            set_some(&mut global.warn_loss, parse("warn_loss", &self.warn_loss, target::parse_loss)?);
+        }
+        if !given("crit_loss") {
+            set_some(&mut global.crit_loss, parse("crit_loss", &self.crit_loss, target::parse_loss)?);
+        }
+        if !given("down_after") {
+            set_some(&mut global.down_after, parse("down_after", &self.down_after, target::parse_probes)?);
+        }
+        if !given("up_after") {
+            set_some(&mut global.up_after, parse("up_after", &self.up_after, target::parse_probes)?);
+        }
+        Ok(())
+    }
+
+    fn apply_ping(self, ping: &mut PingArgs, given: impl Fn(&str) -> bool) -> Result<(), String> {
+        // targets on the command line replace the ones of the config
+        if !given("target") && !given("target_file") {
+            set(&mut ping.target, self.target);
+            set_some(&mut ping.target_file, self.target_file);
+        }
+        if !given("count") {
+            set(&mut ping.count, self.count);
+        }
+        if !given("interval") {
+            set(&mut ping.interval, self.interval);
+        }
+        // so do -4, -6 and --dual-stack, the config may pick a family only when none is given
+        if !given("force_ipv4") && !given("force_ipv6") && !given("dual_stack") {
+            let family = [self.force_ipv4, self.force_ipv6, self.dual_stack];
+            if family.iter().filter(|set| **set == Some(true)).count() > 1 {
+                return Err("only one of force_ipv4, force_ipv6 and dual_stack can be set".to_string());
+            }
+            set(&mut ping.force_ipv4, self.force_ipv4);
+            set(&mut ping.force_ipv6, self.force_ipv6);
+            set(&mut ping.dual_stack, self.dual_stack);
+        }
+        if !given("multiple") {
+            set(&mut ping.multiple, self.multiple);
+        }
+        if !given("resolve_every") {
+            set_some(&mut ping.resolve_every, parse("resolve_every", &self.resolve_every, target::parse_duration)?);
+        }
+        if !given("group") {
+            set_some(&mut ping.group, self.group);
+        }
+        // following a target file is a default, it does nothing without one
+        if !given("watch") {
+            set(&mut ping.watch, self.watch.map(|watch| watch && ping.target_file.is_some()));
+        }
+        if !given("dns_server") {
+            set_some(&mut ping.dns_server, parse("dns_server", &self.dns_server, network::parse_dns_server)?);
+        }
+        if !given("pick_address") {
+            set(&mut ping.pick_address, parse("pick_address", &self.pick_address, str::parse)?);
+        }
+        if !given("resolve_timeout") {
+            set(&mut ping.resolve_timeout, parse("resolve_timeout", &self.resolve_timeout, target::parse_duration)?);
+        }
+        if !given("on_alert") {
+            set_some(&mut ping.on_alert, self.on_alert);
+        }
+        if !given("bell") {
+            set(&mut ping.bell, self.bell);
+        }
+        if !given("webhook") {
+            set_some(&mut ping.webhook, parse("webhook", &self.webhook, alert::parse_webhook)?);
+        }
+        if !given("event_log") {
+            set_some(&mut ping.event_log, self.event_log);
+        }
+        if !given("record") {
+            set_some(&mut ping.record, self.record);
+        }
+        if !given("probe") && !given("port") {
+            set_some(&mut ping.probe, parse("probe", &self.probe, str::parse)?);
+            set_some(&mut ping.port, self.port);
+        }
+        Ok(())
+    }
+}
+
+fn given(matches: &ArgMatches, id: &str) -> bool {
+    matches.value_source(id) == Some(ValueSource::CommandLine)
+}
+
+fn set<T>(option: &mut T, value: Option<T>) {
+    if let Some(value) = value {
+        *option = value;
+    }
+}
+
+fn set_some<T>(option: &mut Option<T>, value: Option<T>) {
+    if value.is_some() {
+        *option = value;
+    }
+}
+
+// a value of the config parsed like the command line option
+fn parse<T>(key: &str, value: &Option<String>, parser: impl Fn(&str) -> Result<T, String>) -> Result<Option<T>, String> {
+    value
+        .as_deref()
+        .map(|value| parser(value).map_err(|err| format!("invalid value for {}: {}", key, err)))
+        .transpose()
+}
+
+// durations, thresholds and address indexes may be given as numbers, `interval = 2`
+fn text<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<String>, D::Error> {
+    match toml::Value::deserialize(deserializer)? {
+        toml::Value::String(value) => Ok(Some(value)),
+        toml::Value::Integer(value) => Ok(Some(value.to_string())),
+        toml::Value::Float(value) => Ok(Some(value.to_string())),
+        value => Err(serde::de::Error::custom(format!("expected a string or a number, found {}", value.type_str()))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    use nping::probe::ProbeKind;
+    use crate::parse_args;
+
+    const CONFIG: &str = r#"
+target = ["1.1.1.1"]
+count = 10
+view_type = "table"
+warn_rtt = "100ms"
+resolve_timeout = "3s"
+bell = true
+watch = true
+force_ipv6 = true
+probe = "udp"
+port = 5353
+
+[profile.wan]
+target = ["8.8.8.8", "9.9.9.9"]
+dual_stack = true
+
+[profile.web]
+probe = "tcp"
+port = 443
+"#;
+
+    fn configured(argv: &str) -> Result<Args, String> {
+        let (mut args, matches) = parse_args(argv.split_whitespace()).map_err(|err| err.to_string())?;
+        let settings = Settings::parse(CONFIG, args.global.profile.as_deref())?;
+        settings.apply(&mut args, &matches)?;
+        Ok(args)
+    }
+
+    #[test]
+    fn command_line_overrides_the_config() {
+        let args = configured("nping -c 5 -v point --resolve-timeout 1s").unwrap();
+        assert_eq!(args.ping.count, 5);
+        assert_eq!(args.global.view_type, "point");
+        assert_eq!(args.ping.resolve_timeout, Duration::from_secs(1));
+        // the rest comes from the config
+        assert_eq!(args.global.warn_rtt, Some(100.0));
+
+        let args = configured("nping").unwrap();
+        assert_eq!(args.ping.count, 10);
+        assert_eq!(args.global.view_type, "table");
+        assert_eq!(args.ping.resolve_timeout, Duration::from_secs(3));
+    }
+
+    #[test]
+    fn switches_of_the_config_can_be_turned_off() {
+        let args = configured("nping").unwrap();
+        assert!(args.ping.bell);
+        let args = configured("nping --bell=false").unwrap();
+        assert!(!args.ping.bell);
+
+        // watch needs a target file
+        assert!(!args.ping.watch);
+        let args = configured("nping -t hosts.txt").unwrap();
+        assert!(args.ping.watch);
+        let args = configured("nping -t hosts.txt --watch=false").unwrap();
+        assert!(!args.ping.watch);
+    }
+
+    #[test]
+    fn address_family_of_the_command_line_replaces_the_config() {
+        let args = configured("nping").unwrap();
+        assert!(args.ping.force_ipv6);
+
+        let args = configured("nping --dual-stack").unwrap();
+        assert!(args.ping.dual_stack && !args.ping.force_ipv6);
+        let args = configured("nping -4").unwrap();
+        assert!(args.ping.force_ipv4 && !args.ping.force_ipv6);
+
+        // a profile picking a family drops the one of the file
+        let args = configured("nping --profile wan").unwrap();
+        assert!(args.ping.dual_stack && !args.ping.force_ipv6);
+
+        let (mut args, matches) = parse_args(["nping"]).unwrap();
+        let both = Settings::parse("force_ipv4 = true\nforce_ipv6 = true", None).unwrap();
+        assert!(both.apply(&mut args, &matches).is_err());
+    }
+
+    #[test]
+    fn targets_of_the_command_line_replace_the_config() {
+        assert_eq!(configured("nping").unwrap().ping.target, ["1.1.1.1"]);
+        assert_eq!(configured("nping --profile wan").unwrap().ping.target, ["8.8.8.8", "9.9.9.9"]);
+        assert_eq!(configured("nping --profile wan 10.0.0.1").unwrap().ping.target, ["10.0.0.1"]);
+
+        let args = configured("nping -t hosts.txt").unwrap();
+        assert!(args.ping.target.is_empty());
+        assert_eq!(args.ping.target_file.as_deref(), Some("hosts.txt"));
+    }
+
+    #[test]
+    fn commands_get_the_options_they_take() {
+        let args = configured("nping ping 10.0.0.1").unwrap();
+        let Some(Commands::Ping(ping)) = &args.command else { panic!("not ping: {:?}", args.command) };
+        assert_eq!(ping.target, ["10.0.0.1"]);
+        assert_eq!(ping.count, 10);
+
+        let args = configured("nping report session.jsonl").unwrap();
+        assert_eq!(args.global.view_type, "table");
+        let args = configured("nping report session.jsonl -v graph").unwrap();
+        assert_eq!(args.global.view_type, "graph");
+        let args = configured("nping -v graph report session.jsonl").unwrap();
+        assert_eq!(args.global.view_type, "graph");
+    }
+
+    #[test]
+    fn profiles_pick_the_probe() {
+        let args = configured("nping --profile web").unwrap();
+        assert_eq!((args.ping.probe, args.ping.port), (Some(ProbeKind::Tcp), Some(443)));
+        let args = configured("nping").unwrap();
+        assert_eq!((args.ping.probe, args.ping.port), (Some(ProbeKind::Udp), Some(5353)));
+        // the probe and its port are one choice, the port of the config does not fit http
+        let args = configured("nping --profile web --probe http").unwrap();
+        assert_eq!((args.ping.probe, args.ping.port), (Some(ProbeKind::Http), None));
+    }
+
+    #[test]
+    fn rejects_bad_configs() {
+        assert!(Settings::parse("colour = true", None).unwrap_err().contains("unknown field"));
+        assert!(Settings::parse(CONFIG, Some("lan")).unwrap_err().contains("no profile named lan"));
+        assert!(Settings::parse("[profile.wan]\ncolour = true", Some("wan")).unwrap_err().contains("profile wan"));
+
+        let (mut args, matches) = parse_args(["nping"]).unwrap();
+        let settings = Settings::parse("warn_rtt = \"soon\"", None).unwrap();
+        assert!(settings.apply(&mut args, &matches).unwrap_err().contains("warn_rtt"));
+        let settings = Settings::parse("probe = \"smtp\"", None).unwrap();
+        assert!(settings.apply(&mut args, &matches).unwrap_err().contains("unsupported probe type: smtp"));
+    }
+}
@@ -1,11 +1,19 @@
 use ratatui::backend::{Backend, CrosstermBackend};
-use ratatui::{Terminal};
-use crate::ip_data::IpData;
+use ratatui::{Frame, Terminal};
+use ratatui::layout::Rect;
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Clear, Paragraph};
+use nping::ip_data::IpData;
+use nping::error_log::ErrorLog;
+use nping::status::{format_duration, EventLog};
+use crate::replay::ReplayProgress;
+use crate::app::AppState;
 use std::io::{self, Stdout};
 use std::error::Error;
 use ratatui::crossterm::execute;
 use ratatui::crossterm::terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen};
-use crate::ui::{draw_graph_view, draw_point_view, draw_table_view};
+use crate::ui::{draw_detail_view, draw_error_log_view, draw_event_log_view, draw_graph_view, draw_point_view, draw_table_view};
 
 /// init terminal
 pub fn init_terminal() -> Result<Terminal<CrosstermBackend<Stdout>>, Box<dyn Error>> {
@@ -29,32 +37,120 @@ pub fn restore_terminal(terminal: &mut Terminal<CrosstermBackend<Stdout>>) -> Re
     Ok(())
 }
 
+/// how many rtts of a target are kept for the view, the point view shows many more than the charts
+pub fn point_num(view_type: &str) -> usize {
+    if view_type == "point" {
+        200
+    } else {
+        10
+    }
+}
+
 
 /// draw ui interface
 pub fn draw_interface<B: Backend>(
     terminal: &mut Terminal<B>,
-    view_type: &str,
     ip_data: &[IpData],
-    errs: &[String],
+    errs: &ErrorLog,
+    events: &EventLog,
+    state: &AppState,
 ) -> Result<(), Box<dyn Error>> {
     terminal.draw(|f| {
-        match view_type {
-            "graph" => {
-                draw_graph_view::<B>(f, ip_data, errs);
-            }
-            "table" => {
-                let size = f.area();
-                draw_table_view::<B>(f, ip_data, errs, size);
-            }
-            "point" => {
-                let size = f.area();
-                draw_point_view::<B>(f, ip_data, errs, size);
-            }
-
-            _ => {
-                draw_graph_view::<B>(f, ip_data, errs);
-            }
+        if state.show_errors {
+            draw_error_log_view(f, errs, f.area());
+        } else if state.show_events {
+            draw_event_log_view(f, ip_data, events, state.now(), f.area());
+        } else if let Some(data) = state.detail_target(ip_data) {
+            draw_detail_view(f, data, errs, &state.thresholds, state.now(), f.area());
+        } else {
+            draw_view(f, ip_data, errs, state);
+        }
+
+        if state.is_paused() {
+            draw_paused_indicator(f);
+        }
+        if let Some(replay) = &state.replay {
+            draw_replay_bar(f, replay);
+        }
+        if let Some(input) = &state.input {
+            draw_input_prompt(f, input);
         }
     })?;
     Ok(())
-}
\ No newline at end of file
+}
+
+fn draw_view(f: &mut Frame, ip_data: &[IpData], errs: &ErrorLog, state: &AppState) {
+    match state.view_type.as_str() {
+        "graph" => {
+            draw_graph_view(f, ip_data, errs, state);
+        }
+        "table" => {
+            let size = f.area();
+            draw_table_view(f, ip_data, errs, size, state);
+        }
+        "point" => {
+            let size = f.area();
+            draw_point_view(f, ip_data, errs, size, state);
+        }
+
+        _ => {
+            draw_graph_view(f, ip_data, errs, state);
+        }
+    }
+}
+
+// show a marker in the top right corner while probing is paused
+fn draw_paused_indicator(f: &mut Frame) {
+    let text = " ⏸ PAUSED (p to resume) ";
+    let size = f.area();
+    let width = (text.chars().count() as u16).min(size.width);
+    let area = Rect::new(size.x + size.width - width, size.y, width, 1.min(size.height));
+    let paragraph = Paragraph::new(text)
+        .style(Style::default().fg(Color::Black).bg(Color::Yellow).add_modifier(Modifier::BOLD));
+    f.render_widget(paragraph, area);
+}
+
+// the add target prompt on the last line of the screen
+fn draw_input_prompt(f: &mut Frame, input: &str) {
+    let size = f.area();
+    if size.height == 0 {
+        return;
+    }
+    let area = Rect::new(size.x, size.y + size.height - 1, size.width, 1);
+    let prompt = Line::from(vec![
+        Span::styled(" Add target: ", Style::default().fg(Color::Black).bg(Color::Cyan)),
+        Span::raw(" "),
+        Span::raw(input.to_string()),
+        Span::styled("█", Style::default().add_modifier(Modifier::SLOW_BLINK)),
+        Span::styled("  (Enter to add, Esc to cancel)", Style::default().fg(Color::DarkGray)),
+    ]);
+    f.render_widget(Clear, area);
+    f.render_widget(Paragraph::new(prompt), area);
+}
+
+// the position, time and speed of a replay on the last line of the screen
+fn draw_replay_bar(f: &mut Frame, replay: &ReplayProgress) {
+    let size = f.area();
+    if size.height == 0 {
+        return;
+    }
+    let area = Rect::new(size.x, size.y + size.height - 1, size.width, 1);
+    let bar = Line::from(vec![
+        Span::styled(
+            if replay.playing { " ▶ Replay " } else { " ⏸ Replay " },
+            Style::default().fg(Color::Black).bg(Color::Cyan),
+        ),
+        Span::raw(format!("{} ", replay.time().format("%Y-%m-%d %H:%M:%S"))),
+        Span::styled(
+            format!("{} / {}", format_duration(replay.position), format_duration(replay.duration)),
+            Style::default().fg(Color::Green),
+        ),
+        Span::raw(format!("  {}x", replay.speed)),
+        Span::styled(
+            "  (Space play/pause, ←/→ seek 10s, PgUp/PgDn 1m, Home/End, +/- speed)",
+            Style::default().fg(Color::DarkGray),
+        ),
+    ]);
+    f.render_widget(Clear, area);
+    f.render_widget(Paragraph::new(bar), area);
+}
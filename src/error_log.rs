@@ -0,0 +1,102 @@
+use std::collections::VecDeque;
+use std::fmt;
+use chrono::{DateTime, Local};
+
+/// maximum number of errors kept, older ones are dropped
+pub const ERROR_LOG_SIZE: usize = 500;
+
+/// what went wrong, shown next to the message
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorKind {
+    /// the host could not be resolved
+    Resolve,
+    /// a probe failed with something other than a timeout
+    Ping,
+    /// the probe stream of a target ended, the target is not probed anymore
+    Stopped,
+    /// an invalid or duplicate target was given
+    Target,
+    /// an alert could not be delivered
+    Alert,
+    /// the session could not be recorded
+    Record,
+}
+
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            ErrorKind::Resolve => "resolve",
+            ErrorKind::Ping => "ping",
+            ErrorKind::Stopped => "stopped",
+            ErrorKind::Target => "target",
+            ErrorKind::Alert => "alert",
+            ErrorKind::Record => "record",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// an error of a target or of nping itself
+#[derive(Debug, Clone)]
+pub struct ErrorRecord {
+    /// id of the target the error belongs to, None if it is not tied to a target
+    pub target_id: Option<usize>,
+    /// display name of the target
+    pub target: String,
+    /// when the error happened
+    pub time: DateTime<Local>,
+    /// what went wrong
+    pub kind: ErrorKind,
+    /// the error as shown in the error log
+    pub message: String,
+}
+
+/// bounded log of the errors of all targets
+#[derive(Debug, Clone, Default)]
+pub struct ErrorLog {
+    records: VecDeque<ErrorRecord>,
+    /// total number of errors ever recorded, including dropped ones
+    total: usize,
+}
+
+impl ErrorLog {
+    /// record an error now, dropping the oldest one when the log is full
+    pub fn push(&mut self, target_id: Option<usize>, target: &str, kind: ErrorKind, message: impl Into<String>) {
+        self.records.push_back(ErrorRecord {
+            target_id,
+            target: target.to_string(),
+            time: Local::now(),
+            kind,
+            message: message.into(),
+        });
+        self.total += 1;
+        if self.records.len() > ERROR_LOG_SIZE {
+            self.records.pop_front();
+        }
+    }
+
+    /// no error was kept
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+
+    /// number of errors ever recorded, including dropped ones
+    pub fn total(&self) -> usize {
+        self.total
+    }
+
+    /// errors from newest to oldest
+    pub fn recent(&self) -> impl Iterator<Item = &ErrorRecord> {
+        self.records.iter().rev()
+    }
+
+    /// errors of one target from newest to oldest
+    pub fn for_target(&self, id: usize) -> impl Iterator<Item = &ErrorRecord> {
+        self.recent().filter(move |record| record.target_id == Some(id))
+    }
+
+    /// number of kept errors of one target
+    pub fn count_for(&self, id: usize) -> usize {
+        self.for_target(id).count()
+    }
+}
@@ -0,0 +1,111 @@
+use crate::ip_data::IpData;
+use crate::status::{DOWN_AFTER, UP_AFTER};
+use crate::target::TargetOptions;
+use crate::stats::{calculate_avg_rtt, calculate_loss_pkg};
+
+/// how healthy a probe, a target or a group is, worst last
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub enum Health {
+    /// within the thresholds
+    #[default]
+    Ok,
+    /// above a warning threshold
+    Warn,
+    /// above a critical threshold
+    Crit,
+}
+
+/// the latency and loss above which something is a warning or critical
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Thresholds {
+    /// rtt thresholds in milliseconds, without them an rtt is only
+    /// a warning when it is close to the highest rtt of the target
+    pub warn_rtt: Option<f64>,
+    /// rtt above which a probe is critical, in milliseconds
+    pub crit_rtt: Option<f64>,
+    /// loss above which a target is a warning, in percent
+    pub warn_loss: f64,
+    /// loss above which a target is critical, in percent
+    pub crit_loss: f64,
+    /// timeouts in a row after which a target is down
+    pub down_after: usize,
+    /// replies in a row after which a down target is up again
+    pub up_after: usize,
+}
+
+/// an rtt above this share of the highest rtt is a warning when no rtt thresholds are set
+const RELATIVE_WARN_RTT: f64 = 0.8;
+
+impl Default for Thresholds {
+    fn default() -> Self {
+        Self {
+            warn_rtt: None,
+            crit_rtt: None,
+            warn_loss: 0.0,
+            crit_loss: 50.0,
+            down_after: DOWN_AFTER,
+            up_after: UP_AFTER,
+        }
+    }
+}
+
+impl Thresholds {
+    /// the thresholds with the overrides of a target applied
+    pub fn for_target(&self, options: &TargetOptions) -> Self {
+        Self {
+            warn_rtt: options.warn_rtt.or(self.warn_rtt),
+            crit_rtt: options.crit_rtt.or(self.crit_rtt),
+            warn_loss: options.warn_loss.unwrap_or(self.warn_loss),
+            crit_loss: options.crit_loss.unwrap_or(self.crit_loss),
+            down_after: options.down_after.unwrap_or(self.down_after),
+            up_after: options.up_after.unwrap_or(self.up_after),
+        }
+    }
+
+    /// a single rtt in milliseconds of a target whose highest rtt is `max_rtt`, timeouts are critical
+    pub fn rtt(&self, rtt: f64, max_rtt: f64) -> Health {
+        if rtt < 0.0 {
+            return Health::Crit;
+        }
+        match (self.warn_rtt, self.crit_rtt) {
+            (None, None) if rtt > max_rtt * RELATIVE_WARN_RTT => Health::Warn,
+            (_, Some(crit)) if rtt > crit => Health::Crit,
+            (Some(warn), _) if rtt > warn => Health::Warn,
+            _ => Health::Ok,
+        }
+    }
+
+    /// a loss rate in percent
+    pub fn loss(&self, loss: f64) -> Health {
+        if loss > self.crit_loss {
+            Health::Crit
+        } else if loss > self.warn_loss {
+            Health::Warn
+        } else {
+            Health::Ok
+        }
+    }
+
+    /// the average rtt of a target, only judged when rtt thresholds are set
+    pub fn avg_rtt(&self, data: &IpData) -> Health {
+        self.avg(calculate_avg_rtt(&data.rtts), data.max_rtt)
+    }
+
+    /// the average rtt of a target over the whole session, only judged when rtt thresholds are set
+    pub fn session_avg_rtt(&self, data: &IpData) -> Health {
+        self.avg(data.session_avg_rtt(), data.max_rtt)
+    }
+
+    fn avg(&self, avg_rtt: f64, max_rtt: f64) -> Health {
+        if (self.warn_rtt.is_some() || self.crit_rtt.is_some()) && avg_rtt > 0.0 {
+            self.rtt(avg_rtt, max_rtt)
+        } else {
+            Health::Ok
+        }
+    }
+
+    /// a target by its loss and its average rtt
+    pub fn target(&self, data: &IpData) -> Health {
+        self.loss(calculate_loss_pkg(data.timeout, data.received)).max(self.avg_rtt(data))
+    }
+}
@@ -1,14 +1,268 @@
 use std::collections::VecDeque;
+use chrono::{DateTime, Local};
+use crate::health::Thresholds;
+use crate::network::{IpFamily, PingEvent};
+use crate::stats::calculate_percentile;
+use crate::status::{update_status, EventLog, StatusTracker};
+use crate::target::{TargetOptions, TargetSpec};
 
+/// number of probes kept for the detail view
+pub const HISTORY_SIZE: usize = 600;
+
+/// a single probe result with the time it was recorded
+#[derive(Debug, Clone)]
+pub struct ProbeRecord {
+    /// number of the probe, counted from 1
+    pub seq: usize,
+    /// when the result arrived
+    pub time: DateTime<Local>,
+    /// the address that was probed
+    pub ip: String,
+    /// rtt in milliseconds, -1.0 on timeout
+    pub rtt: f64,
+}
+
+/// the target was re-resolved to a different address
+#[derive(Debug, Clone)]
+pub struct DnsChange {
+    /// number of probes sent before the change
+    pub seq: usize,
+    /// when the change was seen
+    pub time: DateTime<Local>,
+    /// the address probed before
+    pub old_ip: String,
+    /// the address probed from now on
+    pub new_ip: String,
+}
+
+/// the results and statistics of a target
 #[derive(Debug, Clone)]
 pub struct IpData {
-    pub(crate) addr: String,
-    pub(crate) ip: String,
-    pub(crate) rtts: VecDeque<f64>,
-    pub(crate) last_attr: f64,
-    pub(crate) min_rtt: f64,
-    pub(crate) max_rtt: f64,
-    pub(crate) timeout: usize,
-    pub(crate) received: usize,
-    pub(crate) pop_count: usize,
+    /// identifies the target and its ping task
+    pub id: usize,
+    /// the target as given, a host or an address
+    pub addr: String,
+    /// the address that is probed
+    pub ip: String,
+    /// the most recent rtts in milliseconds, -1.0 for a timeout
+    pub rtts: VecDeque<f64>,
+    /// the latest rtt, -1.0 for a timeout
+    pub last_attr: f64,
+    /// lowest rtt of the session, 0.0 before the first reply
+    pub min_rtt: f64,
+    /// highest rtt of the session
+    pub max_rtt: f64,
+    /// number of timeouts of the session
+    pub timeout: usize,
+    /// number of replies of the session
+    pub received: usize,
+    /// number of rtts dropped from the front of rtts
+    pub pop_count: usize,
+    /// sum of the rtts of every reply, rtts only keeps the last point_num
+    rtt_sum: f64,
+    /// sum of the differences between consecutive probes of the whole session
+    jitter_sum: f64,
+    /// all addresses the target resolved to
+    pub addresses: Vec<String>,
+    /// the most recent probes, longer than rtts
+    pub history: VecDeque<ProbeRecord>,
+    /// address changes seen by re-resolving the target
+    pub dns_changes: Vec<DnsChange>,
+    /// resolving the target failed and is being retried
+    pub unresolved: bool,
+    /// the address family of the row in dual-stack mode
+    pub family: Option<IpFamily>,
+    /// name shown instead of addr
+    pub label: Option<String>,
+    /// the group the target is shown in
+    pub group: Option<String>,
+    /// settings the target file gave the target
+    pub options: TargetOptions,
+    /// up, degraded or down, with the outages so far
+    pub status: StatusTracker,
+}
+
+impl IpData {
+    /// a target without results, probed on `ip`
+    pub fn new(id: usize, addr: String, ip: String) -> Self {
+        Self {
+            id,
+            addr,
+            ip,
+            rtts: VecDeque::new(),
+            last_attr: 0.0,
+            min_rtt: 0.0,
+            max_rtt: 0.0,
+            timeout: 0,
+            received: 0,
+            pop_count: 0,
+            rtt_sum: 0.0,
+            jitter_sum: 0.0,
+            addresses: Vec::new(),
+            history: VecDeque::new(),
+            dns_changes: Vec::new(),
+            unresolved: false,
+            family: None,
+            label: None,
+            group: None,
+            options: TargetOptions::default(),
+            status: StatusTracker::default(),
+        }
+    }
+
+    /// a target with the label, group and options of the spec
+    pub fn for_target(id: usize, target: &TargetSpec, ip: String) -> Self {
+        Self {
+            label: target.label.clone(),
+            group: target.group.clone(),
+            options: target.options.clone(),
+            ..Self::new(id, target.host.clone(), ip)
+        }
+    }
+
+    /// the label of the target, or its address
+    pub fn name(&self) -> &str {
+        self.label.as_deref().unwrap_or(&self.addr)
+    }
+
+    /// average rtt of every reply since the start, not only of the rtts shown
+    pub fn session_avg_rtt(&self) -> f64 {
+        if self.received > 0 {
+            self.rtt_sum / self.received as f64
+        } else {
+            0.0
+        }
+    }
+
+    /// a percentile of the rtts of the history, which is the whole session until HISTORY_SIZE probes were sent
+    pub fn history_percentile(&self, percentile: f64) -> f64 {
+        let rtts: VecDeque<f64> = self.history.iter().map(|record| record.rtt).collect();
+        calculate_percentile(&rtts, percentile)
+    }
+
+    /// mean difference between consecutive probes since the start, like calculate_jitter over rtts
+    pub fn session_jitter(&self) -> f64 {
+        let sent = self.received + self.timeout;
+        if sent > 1 {
+            self.jitter_sum / (sent - 1) as f64
+        } else {
+            0.0
+        }
+    }
+
+    /// add a reply, keeping the last `point_num` rtts
+    pub fn update_rtt(&mut self, rtt: f64, point_num: usize, time: DateTime<Local>) {
+        self.add_jitter(rtt);
+        self.received += 1;
+        self.rtt_sum += rtt;
+        self.last_attr = rtt;
+        self.rtts.push_back(rtt);
+        if self.min_rtt == 0.0 || rtt < self.min_rtt {
+            self.min_rtt = rtt;
+        }
+        if rtt > self.max_rtt {
+            self.max_rtt = rtt;
+        }
+        self.trim_rtts(point_num);
+        self.record(rtt, time);
+    }
+
+    /// add a timeout, keeping the last `point_num` rtts
+    pub fn update_timeout(&mut self, point_num: usize, time: DateTime<Local>) {
+        self.add_jitter(-1.0);
+        self.rtts.push_back(-1.0);
+        self.last_attr = -1.0;
+        self.timeout += 1;
+        self.trim_rtts(point_num);
+        self.record(-1.0, time);
+    }
+
+    /// apply the result of (re-)resolving the target, recording a change of the probed address
+    pub fn update_address(&mut self, ip: String, addresses: Vec<String>, time: DateTime<Local>) {
+        if !self.ip.is_empty() && self.ip != ip {
+            self.dns_changes.push(DnsChange {
+                seq: self.received + self.timeout,
+                time,
+                old_ip: self.ip.clone(),
+                new_ip: ip.clone(),
+            });
+        }
+        self.ip = ip;
+        self.addresses = addresses;
+        self.unresolved = false;
+    }
+
+    /// clear all counters and the rtt history, keeping the target and its settings
+    pub fn reset(&mut self) {
+        *self = IpData {
+            addresses: std::mem::take(&mut self.addresses),
+            unresolved: self.unresolved,
+            family: self.family,
+            label: self.label.take(),
+            group: self.group.take(),
+            options: std::mem::take(&mut self.options),
+            ..IpData::new(self.id, self.addr.clone(), self.ip.clone())
+        };
+    }
+
+    // timeouts count as -1.0 here as well, so the jitter matches the one of the views
+    fn add_jitter(&mut self, rtt: f64) {
+        if self.received + self.timeout > 0 {
+            self.jitter_sum += (rtt - self.last_attr).abs();
+        }
+    }
+
+    fn record(&mut self, rtt: f64, time: DateTime<Local>) {
+        self.history.push_back(ProbeRecord {
+            seq: self.received + self.timeout,
+            time,
+            ip: self.ip.clone(),
+            rtt,
+        });
+        if self.history.len() > HISTORY_SIZE {
+            self.history.pop_front();
+        }
+    }
+
+    fn trim_rtts(&mut self, point_num: usize) {
+        if self.rtts.len() > point_num {
+            self.rtts.pop_front();
+            self.pop_count += 1;
+        }
+    }
+}
+
+/// apply a probe result of a target that was recorded at `time`
+pub fn apply_event(
+    ip_data: &mut [IpData],
+    event: PingEvent,
+    point_num: usize,
+    thresholds: &Thresholds,
+    events: &mut EventLog,
+    time: DateTime<Local>,
+) {
+    match event {
+        PingEvent::Pong { id, rtt } => {
+            if let Some(data) = ip_data.iter_mut().find(|d| d.id == id) {
+                data.update_rtt(rtt, point_num, time);
+                update_status(data, thresholds, events, time);
+            }
+        }
+        PingEvent::Timeout { id } => {
+            if let Some(data) = ip_data.iter_mut().find(|d| d.id == id) {
+                data.update_timeout(point_num, time);
+                update_status(data, thresholds, events, time);
+            }
+        }
+        PingEvent::Resolved { id, ip, addresses } => {
+            if let Some(data) = ip_data.iter_mut().find(|d| d.id == id) {
+                data.update_address(ip, addresses, time);
+            }
+        }
+        PingEvent::Unresolved { id } => {
+            if let Some(data) = ip_data.iter_mut().find(|d| d.id == id) {
+                data.unresolved = true;
+            }
+        }
+    }
 }
@@ -0,0 +1,30 @@
+//! Probing, statistics and target parsing of nping, usable without the tui.
+//!
+//! [`pinger::ping`] streams the results of pinging a single host, [`pinger::ping_with`]
+//! does the same over any [`pinger::Prober`], so tests can script replies with a
+//! [`pinger::MockProber`] instead of sending icmp from a raw socket. [`ip_data::IpData`]
+//! accumulates the results of a target, [`stats`] derives the averages, loss and jitter
+//! from them and [`target`] parses hosts, subnets, ranges and target files.
+//! [`network::PingTaskManager`] runs a ping task per target and sends their results
+//! as [`network::PingEvent`]s, [`health`] and [`status`] judge the results against
+//! the thresholds and [`error_log`] keeps the errors of the tasks.
+#![warn(missing_docs)]
+
+/// pinging a single host over a prober
+pub mod pinger;
+/// the icmp, tcp, udp, http and simulated probers
+pub mod probe;
+/// resolving targets and running their ping tasks
+pub mod network;
+/// the results and statistics of a target
+pub mod ip_data;
+/// averages, loss, jitter and percentiles of rtts
+pub mod stats;
+/// hosts, subnets, ranges and target files
+pub mod target;
+/// warning and critical thresholds of rtt and loss
+pub mod health;
+/// up, degraded and down states of a target and their changes
+pub mod status;
+/// the errors of the targets and of nping itself
+pub mod error_log;
@@ -1,35 +1,129 @@
-mod network;
+mod config;
+mod app;
+mod alert;
 mod draw;
+mod record;
+mod replay;
+mod report;
 mod terminal;
-mod ip_data;
 mod ui;
-mod pinger;
 
-use clap::Parser;
-use std::collections::{HashSet, VecDeque};
+use clap::{ArgAction, ArgMatches, CommandFactory, FromArgMatches, Parser, Subcommand};
+use clap::error::ErrorKind as ClapErrorKind;
+use clap::parser::ValueSource;
+use nping::{network, target};
+use std::collections::HashSet;
 use std::sync::{Arc, Mutex};
-use tokio::task;
-use crate::ip_data::IpData;
+use tokio::runtime::Handle;
+use nping::ip_data::{self, IpData};
 use std::sync::mpsc;
 use std::thread;
+use std::net::SocketAddr;
 use std::time::Duration;
-use std::path::Path;
+use chrono::Local;
+use std::path::{Path, PathBuf};
+use std::io::{self, IsTerminal};
 use ratatui::crossterm::event;
-use ratatui::crossterm::event::{Event, KeyCode, KeyModifiers};
-use crate::network::send_ping;
+use ratatui::crossterm::event::{Event, KeyEvent, KeyEventKind};
+use crate::app::{AppState, KeyAction};
+use nping::error_log::{ErrorKind, ErrorLog};
+use nping::health::Thresholds;
+use crate::alert::{AlertOptions, Alerter};
+use nping::status::EventLog;
+use crate::record::Recorder;
+use nping::probe::ProbeKind;
+use nping::target::{TargetOptions, TargetSpec};
+use nping::network::{AddressPick, IpFamily, PingContext, PingEvent, PingTaskManager, ResolveOptions};
 
 #[derive(Parser, Debug)]
 #[command(
     version = "v0.3.0",
     author = "hanshuaikang<https://github.com/hanshuaikang>",
-    about = "🏎  Nping mean NB Ping, A Ping Tool in Rust with Real-Time Data and Visualizations"
+    about = "🏎  Nping mean NB Ping, A Ping Tool in Rust with Real-Time Data and Visualizations",
+    after_help = "The options of pinging are listed by `nping ping --help`"
 )]
 struct Args {
-    /// Target IP address or hostname to ping
-    #[arg(help = "target IP address or hostname to ping", required = false)]
+    #[command(subcommand)]
+    command: Option<Commands>,
+
+    /// `nping host ...` is the same as `nping ping host ...`
+    #[command(flatten)]
+    ping: PingArgs,
+
+    #[command(flatten)]
+    global: GlobalArgs,
+}
+
+#[derive(Subcommand, Debug)]
+enum Commands {
+    /// Ping targets with live views, the default when no command is given
+    Ping(Box<PingArgs>),
+    /// Show a session recorded with --record, with play/pause, seeking and speed control
+    Replay {
+        /// file written by --record
+        file: PathBuf,
+    },
+    /// Print the statistics, outages and state changes of a recorded session
+    Report {
+        /// file written by --record
+        file: PathBuf,
+    },
+    /// Compare the statistics of the targets of two recorded sessions
+    Compare {
+        /// the recording to compare against
+        before: PathBuf,
+        /// the recording compared to it
+        after: PathBuf,
+    },
+}
+
+/// options shared by all commands
+#[derive(clap::Args, Debug, Clone)]
+struct GlobalArgs {
+    #[arg(short, long, global = true, default_value = "graph", help = "view mode graph/table/point")]
+    view_type: String,
+
+    /// Latency above which an rtt is a warning, e.g. 100ms, target files can set it per target
+    #[arg(long, global = true, value_parser = target::parse_rtt, help = "Rtt above which a target is a warning (e.g. 100ms)")]
+    warn_rtt: Option<f64>,
+
+    /// Latency above which an rtt is critical
+    #[arg(long, global = true, value_parser = target::parse_rtt, help = "Rtt above which a target is critical (e.g. 300ms)")]
+    crit_rtt: Option<f64>,
+
+    /// Loss above which a target is a warning, in percent, defaults to any loss
+    #[arg(long, global = true, value_parser = target::parse_loss, help = "Loss above which a target is a warning (e.g. 1%) [default: 0%]")]
+    warn_loss: Option<f64>,
+
+    /// Loss above which a target is critical, in percent
+    #[arg(long, global = true, value_parser = target::parse_loss, help = "Loss above which a target is critical (e.g. 20%) [default: 50%]")]
+    crit_loss: Option<f64>,
+
+    /// Timeouts in a row after which a target is down, target files can set it per target
+    #[arg(long, global = true, value_parser = target::parse_probes, help = "Timeouts in a row after which a target is down [default: 3]")]
+    down_after: Option<usize>,
+
+    /// Replies in a row after which a down target is up again
+    #[arg(long, global = true, value_parser = target::parse_probes, help = "Replies in a row after which a down target is up [default: 2]")]
+    up_after: Option<usize>,
+
+    /// Config file with defaults for the options and named profiles, defaults to ~/.config/nping/config.toml
+    #[arg(long, global = true, help = "Config file, defaults to ~/.config/nping/config.toml")]
+    config: Option<PathBuf>,
+
+    /// Named profile of the config file whose targets and options are used
+    #[arg(long, global = true, help = "Use a named profile of the config file")]
+    profile: Option<String>,
+}
+
+/// options of pinging targets live
+#[derive(clap::Args, Debug, Clone)]
+struct PingArgs {
+    /// Target IP address or hostname to ping, subnets and ranges are expanded into their addresses
+    #[arg(help = "target IP address, hostname, subnet (10.0.0.0/28) or range (10.0.0.10-20) to ping", required = false)]
     target: Vec<String>,
 
-    #[arg(short, long, help = "target list file", required = false)]
+    #[arg(short, long, help = "target list file, - reads the targets from stdin", required = false)]
     target_file: Option<String>,
 
     /// Number of pings to send, when count is 0, the maximum number of pings per address is calculated
@@ -40,86 +134,225 @@ struct Args {
     #[arg(short, long, default_value_t = 0, help = "Interval in seconds between pings")]
     interval: i32,
 
-    #[clap(long = "force_ipv6", default_value_t = false, short = '6', help = "Force using IPv6")]
+    /// IPv4 is the default, -4 replaces force_ipv6 or dual_stack of the config file
+    #[clap(long = "force_ipv4", short = '4', action = ArgAction::Set, num_args = 0..=1, require_equals = true, default_missing_value = "true", default_value_t = false, value_name = "BOOL", hide_default_value = true, hide_possible_values = true, conflicts_with = "force_ipv6", help = "Force using IPv4, the default unless the config file picks IPv6")]
+    pub force_ipv4: bool,
+
+    #[clap(long = "force_ipv6", short = '6', action = ArgAction::Set, num_args = 0..=1, require_equals = true, default_missing_value = "true", default_value_t = false, value_name = "BOOL", hide_default_value = true, hide_possible_values = true, help = "Force using IPv6")]
     pub force_ipv6: bool,
 
+    /// Ping every hostname over both IPv4 and IPv6, shown as a pair of rows
+    #[arg(long, action = ArgAction::Set, num_args = 0..=1, require_equals = true, default_missing_value = "true", default_value_t = false, value_name = "BOOL", hide_default_value = true, hide_possible_values = true, conflicts_with_all = ["force_ipv4", "force_ipv6"], help = "Ping both the IPv4 and IPv6 address of every hostname")]
+    dual_stack: bool,
+
     #[arg(
         short = 'm',
         long,
         default_value_t = 0,
-        help = "Specify the maximum number of target addresses, Only works on one target address"
+        help = "Specify the maximum number of addresses pinged per hostname"
     )]
     multiple: i32,
 
-    #[arg(short, long, default_value = "graph", help = "view mode graph/table/point")]
-    view_type: String,
+    /// Re-resolve hostnames periodically and follow address changes, e.g. 30s, 5m, 1h
+    #[arg(long, value_parser = target::parse_duration, help = "Re-resolve hostnames every duration (e.g. 30s, 5m)")]
+    resolve_every: Option<Duration>,
+
+    /// Group of the command line targets that are not given as group:host
+    #[arg(short, long, help = "Group of the command line targets, or give targets as group:host")]
+    group: Option<String>,
+
+    /// Reload the target file when it changes, adding new targets and stopping removed ones
+    #[arg(long, action = ArgAction::Set, num_args = 0..=1, require_equals = true, default_missing_value = "true", default_value_t = false, value_name = "BOOL", hide_default_value = true, hide_possible_values = true, help = "Reload the target file when it changes")]
+    watch: bool,
+
+    /// DNS server used to resolve hostnames instead of the system resolver, e.g. 1.1.1.1 or [2606:4700::1111]:53
+    #[arg(long, value_parser = network::parse_dns_server, help = "DNS server to resolve hostnames with, ip[:port]")]
+    dns_server: Option<SocketAddr>,
+
+    /// Which resolved address of a hostname to ping: first, all (one row each) or an index
+    #[arg(long, default_value = "first", help = "Address of a hostname to ping: first/all/<index>")]
+    pick_address: AddressPick,
+
+    /// Give up resolving a hostname after this long, unresolved targets are retried
+    #[arg(long, value_parser = target::parse_duration, default_value = "5s", help = "Timeout for resolving a hostname")]
+    resolve_timeout: Duration,
+
+    /// Shell command run when a target crosses a threshold or goes down or up, the alert is in NPING_* variables
+    #[arg(long, help = "Command to run on alerts, gets NPING_EVENT, NPING_TARGET, NPING_LOSS, ...")]
+    on_alert: Option<String>,
+
+    /// Ring the terminal bell on alerts
+    #[arg(long, action = ArgAction::Set, num_args = 0..=1, require_equals = true, default_missing_value = "true", default_value_t = false, value_name = "BOOL", hide_default_value = true, hide_possible_values = true, help = "Ring the terminal bell on alerts")]
+    bell: bool,
+
+    /// Post alerts as json to this url
+    #[arg(long, value_parser = alert::parse_webhook, help = "Post alerts as json to an http:// url")]
+    webhook: Option<String>,
+
+    /// Append every change of a target between up, degraded and down to a csv file
+    #[arg(long, help = "Append target state changes and outages to a csv file")]
+    event_log: Option<PathBuf>,
+
+    /// Record every probe to a file, show it again with `nping replay <file>`
+    #[arg(long, help = "Record the session to a file for nping replay")]
+    record: Option<PathBuf>,
+
+    /// How the targets are probed unless the target file picks a probe for them: icmp, tcp, udp, http or dns
+    #[arg(long, help = "Probe the targets with icmp, tcp, udp, http or dns")]
+    probe: Option<ProbeKind>,
+
+    /// Port of tcp, udp, http and dns probes
+    #[arg(long, help = "Port of the probe, http and dns have a default")]
+    port: Option<u16>,
 }
 
-fn read_target_file(file_path: &Path) -> Result<Vec<String>, Box<dyn std::error::Error>> {
-    use std::fs::File;
-    use std::io::{BufRead, BufReader};
+// the ids of the options of `nping ping`, nping itself takes them too for `nping host ...`
+fn ping_options() -> Vec<String> {
+    <PingArgs as clap::Args>::augment_args(clap::Command::new("ping"))
+        .get_arguments()
+        .map(|arg| arg.get_id().to_string())
+        .collect()
+}
 
-    let file = match File::open(file_path) {
-        Ok(f) => f,
-        Err(e) => return Err(Box::new(e)),
-    };
-    let reader = BufReader::new(file);
+// the parser of Args, nping itself only lists the options shared by all commands in its help
+fn cli() -> clap::Command {
+    let ping = ping_options();
+    Args::command().mut_args(|arg| {
+        let ping_only = arg.get_id() != "target" && ping.iter().any(|id| arg.get_id() == id.as_str());
+        arg.hide(ping_only)
+    })
+}
 
-    let targets = reader
-        .lines()
-        .filter_map(|line| line.ok())
-        .map(|line| line.trim().to_string())
-        .filter(|line| !line.is_empty())
-        .collect();
-    return Ok(targets);
+fn parse_args<I, T>(argv: I) -> Result<(Args, ArgMatches), clap::Error>
+where
+    I: IntoIterator<Item = T>,
+    T: Into<std::ffi::OsString> + Clone,
+{
+    let mut cli = cli();
+    let matches = cli.try_get_matches_from_mut(argv)?;
+    let args = Args::from_arg_matches(&matches).map_err(|err| err.format(&mut cli))?;
+    match matches.subcommand_name() {
+        // `nping --record x report f` must not silently record nothing
+        Some(command) => {
+            let given = ping_options().into_iter().find(|id| matches.value_source(id) == Some(ValueSource::CommandLine));
+            if let Some(arg) = given.and_then(|id| cli.get_arguments().find(|arg| arg.get_id() == id.as_str())) {
+                let message = format!("{} is an option of ping and can't be given before the command {}", arg, command);
+                return Err(cli.error(ClapErrorKind::ArgumentConflict, message));
+            }
+        }
+        // a command after a target is taken for a target, don't ping a host named report
+        None => {
+            if let Some(name) = args.ping.target.iter().find(|target| cli.find_subcommand(target).is_some()) {
+                return Err(cli.error(
+                    ClapErrorKind::ArgumentConflict,
+                    format!("the command {0} must come before the targets, a host named {0} is pinged with `nping ping {0}`", name),
+                ));
+            }
+        }
+    }
+    Ok((args, matches))
+}
+
+impl GlobalArgs {
+    // the thresholds given on the command line, with the defaults for loss and going down and up
+    fn thresholds(&self) -> Thresholds {
+        let defaults = Thresholds::default();
+        Thresholds {
+            warn_rtt: self.warn_rtt,
+            crit_rtt: self.crit_rtt,
+            warn_loss: self.warn_loss.unwrap_or(defaults.warn_loss),
+            crit_loss: self.crit_loss.unwrap_or(defaults.crit_loss),
+            down_after: self.down_after.unwrap_or(defaults.down_after),
+            up_after: self.up_after.unwrap_or(defaults.up_after),
+        }
+    }
 }
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     // parse command line arguments
-    let args = Args::parse();
+    let (mut args, matches) = parse_args(std::env::args_os()).unwrap_or_else(|err| err.exit());
+
+    // options not given on the command line are taken from the config file and the profile
+    let config_path = args.global.config.clone().or_else(|| config::default_config_path().filter(|path| path.exists()));
+    match config_path {
+        Some(path) => {
+            if let Err(err) = config::apply_config(&path, &mut args, &matches) {
+                eprintln!("{}", err);
+                std::process::exit(1);
+            }
+        }
+        None if args.global.profile.is_some() => {
+            eprintln!("--profile needs a config file, none was found");
+            std::process::exit(1);
+        }
+        None => {}
+    }
 
-    // set Ctrl+C and q and esc to exit
+    let global = args.global;
     let running = Arc::new(Mutex::new(true));
-    {
-        let running = running.clone();
-        thread::spawn(move || {
-            loop {
-                // if running is false, exit the loop
-                if !*running.lock().unwrap() {
-                    break;
-                }
-
-                if let Ok(true) = event::poll(Duration::from_millis(50)) {
-                    if let Ok(Event::Key(key)) = event::read() {
-                        match key.code {
-                            KeyCode::Char('q') | KeyCode::Esc => {
-                                *running.lock().unwrap() = false;
-                                break;
-                            },
-                            KeyCode::Char('c') if key.modifiers == KeyModifiers::CONTROL => {
-                                *running.lock().unwrap() = false;
-                                break;
-                            },
-                            _ => {}
-                        }
-                    }
-                }
+    let mut args = match args.command {
+        None => args.ping,
+        Some(Commands::Ping(ping)) => *ping,
+        Some(Commands::Replay { file }) => {
+            let key_rx = read_keys(running);
+            if let Err(err) = replay::run_replay(&file, global.view_type.clone(), global.thresholds(), key_rx) {
+                eprintln!("{}", err);
+                std::process::exit(1);
             }
-        });
+            return Ok(());
+        }
+        Some(Commands::Report { file }) => {
+            if let Err(err) = report::print_report(&file, &global.thresholds()) {
+                eprintln!("{}", err);
+                std::process::exit(1);
+            }
+            return Ok(());
+        }
+        Some(Commands::Compare { before, after }) => {
+            if let Err(err) = report::print_compare(&before, &after, &global.thresholds()) {
+                eprintln!("{}", err);
+                std::process::exit(1);
+            }
+            return Ok(());
+        }
+    };
+    let probe = TargetOptions { probe: args.probe, port: args.port, ..TargetOptions::default() };
+    if let Err(err) = probe.validate() {
+        eprintln!("{}", err);
+        std::process::exit(1);
     }
+    let key_rx = read_keys(running.clone());
 
+    let cli_targets = match parse_targets(&std::mem::take(&mut args.target), args.group.as_deref(), &probe) {
+        Ok(targets) => targets,
+        Err(err) => {
+            eprintln!("{}", err);
+            std::process::exit(1);
+        }
+    };
 
-
-    // after de-duplication, the original order is still preserved
-    let mut seen = HashSet::new();
-    let mut targets: Vec<String> = args.target.into_iter()
-        .filter(|item| seen.insert(item.clone()))
-        .collect();
-
-    if let Some(target_file) = args.target_file {
+    if args.watch && args.target_file.is_none() {
+        eprintln!("--watch needs a target file given with -t");
+        std::process::exit(1);
+    }
+    let mut file_targets = Vec::new();
+    if args.target_file.as_deref() == Some("-") || (args.target_file.is_none() && cli_targets.is_empty() && !io::stdin().is_terminal()) {
+        // targets piped in, keys are still read from the terminal
+        if args.watch {
+            eprintln!("--watch needs a target file, not stdin");
+            std::process::exit(1);
+        }
+        match target::read_target_stdin() {
+            Ok(specs) => file_targets = specs,
+            Err(err) => {
+                eprintln!("{}", err);
+                std::process::exit(1);
+            }
+        }
+    } else if let Some(target_file) = &args.target_file {
         // read target file
-        let path = Path::new(&target_file);
+        let path = Path::new(target_file);
         if !path.exists() {
             eprintln!("Target file does not exist");
             std::process::exit(1);
@@ -128,17 +361,43 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             eprintln!("Target file is not a file");
             std::process::exit(1);
         }
-        let mut targets_from_file = read_target_file(path)?;
-        targets_from_file.retain(|item| seen.insert(item.clone()));
-        targets.extend(targets_from_file);
+        match target::read_target_file(path) {
+            Ok(specs) => file_targets = specs,
+            Err(err) => {
+                eprintln!("{}", err);
+                std::process::exit(1);
+            }
+        }
     }
 
+    // expand subnets and ranges into single addresses, after de-duplication the original order is still preserved
+    let file_targets = match target::expand_targets(with_probe(file_targets, &probe)) {
+        Ok(file_targets) => file_targets,
+        Err(err) => {
+            eprintln!("{}", err);
+            std::process::exit(1);
+        }
+    };
+    // the targets a reload of the target file may remove
+    let file_hosts: HashSet<String> = file_targets
+        .iter()
+        .map(|target| target.host.clone())
+        .filter(|host| !cli_targets.iter().any(|target| target.host == *host))
+        .collect();
+    // a host both on the command line and in the file is pinged once
+    let mut seen = HashSet::new();
+    let targets: Vec<TargetSpec> = cli_targets
+        .into_iter()
+        .chain(file_targets)
+        .filter(|target| seen.insert(target.host.clone()))
+        .collect();
+
     if targets.is_empty() {
         eprintln!("No target specified");
         std::process::exit(1);
     }
 
-    let res = run_app(targets, args.count, args.interval, running.clone(), args.force_ipv6, args.multiple, args.view_type).await;
+    let res = run_app(targets, file_hosts, &args, &global, running.clone(), key_rx).await;
 
     // if error print error message and exit
     if let Err(err) = res {
@@ -149,14 +408,23 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 }
 
 async fn run_app(
-    targets: Vec<String>,
-    count: usize,
-    interval: i32,
+    targets: Vec<TargetSpec>,
+    mut file_hosts: HashSet<String>,
+    args: &PingArgs,
+    global: &GlobalArgs,
     running: Arc<Mutex<bool>>,
-    force_ipv6: bool,
-    multiple: i32,
-    view_type: String,
+    key_rx: mpsc::Receiver<KeyEvent>,
 ) -> Result<(), Box<dyn std::error::Error>> {
+    let count = args.count;
+    let multiple = args.multiple;
+    let view_type = global.view_type.clone();
+
+    // open the event log before taking over the terminal so that errors are readable
+    let mut events = match &args.event_log {
+        Some(path) => EventLog::with_file(path)?,
+        None => EventLog::default(),
+    };
+    let mut recorder = args.record.as_deref().map(Recorder::create).transpose()?;
 
     // init terminal
     draw::init_terminal()?;
@@ -167,109 +435,534 @@ async fn run_app(
 
 
     // ip channel
-    let (ping_update_tx, ping_update_rx) = mpsc::sync_channel::<IpData>(0);
+    let (ping_update_tx, ping_update_rx) = mpsc::sync_channel::<PingEvent>(0);
 
     let ping_update_tx = Arc::new(ping_update_tx);
 
 
-    let mut ips = Vec::new();
-    // if multiple is set, get multiple IP addresses for each target
-    if targets.len() == 1 && multiple > 0 {
-        // get multiple IP addresses for the target
-        ips = network::get_multiple_host_ipaddr(&targets[0], force_ipv6, multiple as usize)?;
-    } else {
-        // get IP address for each target
-        for target in &targets {
-            let ip = network::get_host_ipaddr(target, force_ipv6)?;
-            ips.push(ip);
-        }
-    }
+    let point_num = draw::point_num(&view_type);
 
-    // Define statistics variables
-    let ip_data = Arc::new(Mutex::new(ips.iter().enumerate().map(|(i, _)| IpData {
-        ip: String::new(),
-        addr: if targets.len() == 1 { targets[0].clone() } else { targets[i].clone() },
-        rtts: VecDeque::new(),
-        last_attr: 0.0,
-        min_rtt: 0.0,
-        max_rtt: 0.0,
-        timeout: 0,
-        received: 0,
-        pop_count: 0,
-    }).collect::<Vec<_>>()));
-
-    let mut point_num = 10;
-    if view_type == "point" {
-        point_num = 200;
-    }
+    let paused = Arc::new(Mutex::new(false));
+
+    let errs = Arc::new(Mutex::new(ErrorLog::default()));
+
+    let interval = if args.interval == 0 { 500 } else { args.interval * 1000 };
 
-    let view_type = Arc::new(view_type);
+    let thresholds = global.thresholds();
 
-    let errs = Arc::new(Mutex::new(Vec::new()));
+    // added and reloaded targets get the probe of the command line as well
+    let probe = TargetOptions { probe: args.probe, port: args.port, ..TargetOptions::default() };
 
-    let interval = if interval == 0 { 500 } else { interval * 1000 };
-    let mut tasks = Vec::new();
+    let resolve = ResolveOptions {
+        family: if args.force_ipv6 { IpFamily::V6 } else { IpFamily::V4 },
+        dual_stack: args.dual_stack,
+        timeout: args.resolve_timeout,
+        dns_server: args.dns_server,
+        pick: args.pick_address,
+    };
+
+    let manager = Arc::new(Mutex::new(PingTaskManager::new(
+        Handle::current(),
+        PingContext {
+            count,
+            interval: interval as u64,
+            running: running.clone(),
+            paused: paused.clone(),
+            resolve: resolve.clone(),
+            resolve_every: args.resolve_every,
+            errs: errs.clone(),
+            ping_update_tx,
+        },
+    )));
 
+    // Define statistics variables
+    let ip_data = Arc::new(Mutex::new(Vec::new()));
+    let rows_per_host = rows_per_host(&resolve, multiple);
+    NewTargets::resolve(targets, &resolve, rows_per_host)
+        .await
+        .add(&mut ip_data.lock().unwrap(), &mut manager.lock().unwrap(), &resolve);
 
     {
         let ip_data = ip_data.clone();
         let errs = errs.clone();
         let terminal_guard = terminal_guard.clone();
-        let view_type = view_type.clone();
+        let running = running.clone();
+        let manager = manager.clone();
+        let runtime = Handle::current();
+        let mut state = AppState::new(view_type, paused.clone(), thresholds);
+        let mut alerter = Alerter::new(
+            AlertOptions {
+                command: args.on_alert.clone(),
+                bell: args.bell,
+                webhook: args.webhook.clone(),
+            },
+            errs.clone(),
+        );
+
+        // reload the target file when it changes
+        let (reload_tx, reload_rx) = mpsc::channel();
+        if let (true, Some(target_file)) = (args.watch, &args.target_file) {
+            target::watch_target_file(target_file.into(), running.clone(), reload_tx);
+        }
 
         {
             let mut guard = terminal_guard.lock().unwrap();
             let ip_data = ip_data.lock().unwrap();
             // first draw ui
             draw::draw_interface(
-                &mut guard.terminal.as_mut().unwrap(),
-                &view_type,
+                guard.terminal.as_mut().unwrap(),
                 &ip_data,
-                &mut errs.lock().unwrap(),
+                &errs.lock().unwrap(),
+                &events,
+                &state,
             ).ok();
         }
 
         thread::spawn(move || {
-            while let Ok(updated_data) = ping_update_rx.recv() {
-                let mut ip_data = ip_data.lock().unwrap();
-                if let Some(pos) = ip_data.iter().position(|d| d.addr == updated_data.addr && d.ip == updated_data.ip) {
-                    ip_data[pos] = updated_data;
+            loop {
+                // redraw on every ping update, or periodically to pick up key presses
+                match ping_update_rx.recv_timeout(Duration::from_millis(50)) {
+                    Ok(event) => {
+                        let mut ip_data = ip_data.lock().unwrap();
+                        if let Some(rec) = recorder.as_mut() {
+                            if let Err(err) = rec.sync_targets(&ip_data).and_then(|_| rec.event(&event)) {
+                                // stop recording instead of failing on every following probe
+                                errs.lock().unwrap().push(None, "recording", ErrorKind::Record, format!("recording stopped: {}", err));
+                                recorder = None;
+                            }
+                        }
+                        ip_data::apply_event(&mut ip_data, event, point_num, &state.thresholds, &mut events, Local::now());
+                    }
+                    Err(mpsc::RecvTimeoutError::Timeout) => {}
+                    Err(mpsc::RecvTimeoutError::Disconnected) => break,
+                }
+                alerter.check(&ip_data.lock().unwrap(), &state.thresholds);
+
+                while let Ok(key) = key_rx.try_recv() {
+                    let mut ip_data = ip_data.lock().unwrap();
+                    match state.handle_key(key, &mut ip_data) {
+                        KeyAction::Quit => *running.lock().unwrap() = false,
+                        KeyAction::AddTarget(host) => {
+                            // checked and expanded like the targets of the command line
+                            let targets = match parse_targets(std::slice::from_ref(&host), None, &probe) {
+                                Ok(targets) => targets,
+                                Err(err) => {
+                                    errs.lock().unwrap().push(None, &host, ErrorKind::Target, err);
+                                    continue;
+                                }
+                            };
+                            let targets: Vec<TargetSpec> = targets
+                                .into_iter()
+                                .filter(|target| {
+                                    let exists = ip_data.iter().any(|d| d.addr == target.host);
+                                    if exists {
+                                        errs.lock().unwrap().push(None, &target.host, ErrorKind::Target, "target already exists");
+                                    }
+                                    !exists
+                                })
+                                .collect();
+                            // a target with a row per address is resolved before the manager is locked
+                            runtime
+                                .block_on(NewTargets::resolve(targets, &resolve, rows_per_host))
+                                .add(&mut ip_data, &mut manager.lock().unwrap(), &resolve);
+                        }
+                        KeyAction::RemoveTarget(id) => {
+                            manager.lock().unwrap().remove(id);
+                            if let Some(index) = ip_data.iter().position(|d| d.id == id) {
+                                ip_data.remove(index);
+                                state.target_removed(index, ip_data.len());
+                            }
+                        }
+                        KeyAction::None => {}
+                    }
                 }
+
+                while let Ok(reloaded) = reload_rx.try_recv() {
+                    let mut ip_data = ip_data.lock().unwrap();
+                    match reloaded {
+                        Ok(targets) => {
+                            let targets = with_probe(targets, &probe);
+                            let added: Vec<TargetSpec> = targets
+                                .iter()
+                                .filter(|target| !ip_data.iter().any(|data| data.addr == target.host))
+                                .cloned()
+                                .collect();
+                            let added = runtime.block_on(NewTargets::resolve(added, &resolve, rows_per_host));
+                            let mut manager = manager.lock().unwrap();
+                            file_hosts = reload_targets(&mut ip_data, &mut manager, &resolve, &mut state, &file_hosts, &targets, added);
+                        }
+                        // keep the current targets when the file cannot be read
+                        Err(err) => errs.lock().unwrap().push(None, "target file", ErrorKind::Target, err),
+                    }
+                }
+
+                if !*running.lock().unwrap() {
+                    break;
+                }
+
+                let ip_data = ip_data.lock().unwrap();
                 let mut guard = terminal_guard.lock().unwrap();
                 draw::draw_interface(
-                    &mut guard.terminal.as_mut().unwrap(),
-                    &view_type,
+                    guard.terminal.as_mut().unwrap(),
                     &ip_data,
-                    &mut errs.lock().unwrap(),
+                    &errs.lock().unwrap(),
+                    &events,
+                    &state,
                 ).ok();
             }
         });
     }
-    for (i, ip) in ips.iter().enumerate() {
-        let ip = ip.clone();
-        let running = running.clone();
-        let errs = errs.clone();
-        let task = task::spawn({
-            let errs = errs.clone();
-            let ping_update_tx = ping_update_tx.clone();
-            let ip_data = ip_data.clone();
-            let mut data = ip_data.lock().unwrap();
-            // update the ip
-            data[i].ip = ip.clone();
-            let addr = data[i].addr.clone();
-            async move {
-                send_ping(addr, ip, errs.clone(), count, interval, running.clone(), ping_update_tx, point_num).await.unwrap();
-            }
-        });
-        tasks.push(task)
-    }
 
-    for task in tasks {
-        task.await?;
+    // wait until exit is requested or every ping task has completed its count
+    while *running.lock().unwrap() && !manager.lock().unwrap().all_finished() {
+        tokio::time::sleep(Duration::from_millis(100)).await;
     }
 
+    // stop the ui and key threads before restoring the terminal
+    *running.lock().unwrap() = false;
+
     // restore terminal
-    draw::restore_terminal(&mut terminal_guard.lock().unwrap().terminal.as_mut().unwrap())?;
+    draw::restore_terminal(terminal_guard.lock().unwrap().terminal.as_mut().unwrap())?;
+
+    // the ui that showed why targets stopped is gone
+    for err in stop_errors(&errs.lock().unwrap()) {
+        eprintln!("{}", err);
+    }
+    probed(&ip_data.lock().unwrap())?;
 
     Ok(())
-}
\ No newline at end of file
+}
+
+// the errors that stopped the probing of a target, from oldest to newest
+fn stop_errors(errs: &ErrorLog) -> Vec<String> {
+    let mut stopped: Vec<String> = errs
+        .recent()
+        .filter(|record| matches!(record.kind, ErrorKind::Ping | ErrorKind::Stopped))
+        .map(|record| format!("{}: {}", record.target, record.message))
+        .collect();
+    stopped.reverse();
+    stopped
+}
+
+// fail when not a single probe was sent, like without the privileges for icmp
+fn probed(ip_data: &[IpData]) -> Result<(), String> {
+    if ip_data.iter().any(|data| data.received + data.timeout > 0) {
+        Ok(())
+    } else {
+        Err("no target could be probed".to_string())
+    }
+}
+// read key events in a separate thread until running is cleared, they are handled by the ui thread
+fn read_keys(running: Arc<Mutex<bool>>) -> mpsc::Receiver<KeyEvent> {
+    let (key_tx, key_rx) = mpsc::channel::<KeyEvent>();
+    thread::spawn(move || {
+        loop {
+            // if running is false, exit the loop
+            if !*running.lock().unwrap() {
+                break;
+            }
+
+            if let Ok(true) = event::poll(Duration::from_millis(50)) {
+                if let Ok(Event::Key(key)) = event::read() {
+                    if key.kind == KeyEventKind::Press && key_tx.send(key).is_err() {
+                        break;
+                    }
+                }
+            }
+        }
+    });
+    key_rx
+}
+
+// targets given like on the command line, `group` is the group of the ones not given as group:host,
+// subnets and ranges are expanded into their addresses
+fn parse_targets(targets: &[String], group: Option<&str>, probe: &TargetOptions) -> Result<Vec<TargetSpec>, String> {
+    let targets = targets
+        .iter()
+        .map(|target| TargetSpec::parse(target))
+        .map(|target| match (&target.group, group) {
+            (None, Some(group)) => TargetSpec { group: Some(group.to_string()), ..target },
+            _ => target,
+        })
+        .collect();
+    target::expand_targets(with_probe(targets, probe))
+}
+
+// the probe and port of the command line for the targets that pick no probe themselves
+fn with_probe(targets: Vec<TargetSpec>, probe: &TargetOptions) -> Vec<TargetSpec> {
+    targets
+        .into_iter()
+        .map(|mut target| {
+            if target.options.probe.is_none() && target.options.port.is_none() {
+                target.options.probe = probe.probe;
+                target.options.port = probe.port;
+            }
+            target
+        })
+        .collect()
+}
+
+// how many rows a hostname gets with -m or --pick-address all, None for the single row of
+// --pick-address first or <index>
+fn rows_per_host(resolve: &ResolveOptions, multiple: i32) -> Option<usize> {
+    if multiple > 0 {
+        Some(multiple as usize)
+    } else {
+        (resolve.pick == AddressPick::All).then_some(usize::MAX)
+    }
+}
+
+// targets about to be added, from the command line, the target file or the `a` prompt
+struct NewTargets {
+    targets: Vec<TargetSpec>,
+    rows_per_host: Option<usize>,
+    // with a row per address, the addresses of every family of every target in order, None if unresolved
+    addresses: Vec<Option<Vec<String>>>,
+}
+
+impl NewTargets {
+    // with a row per address the rows depend on the addresses, so the targets are resolved up front and concurrently
+    async fn resolve(targets: Vec<TargetSpec>, resolve: &ResolveOptions, rows_per_host: Option<usize>) -> Self {
+        let mut addresses = Vec::new();
+        if rows_per_host.is_some() {
+            let lookups: Vec<_> = targets
+                .iter()
+                .flat_map(|target| resolve.families(&target.host).into_iter().map(move |family| (target, family)))
+                .map(|(target, family)| {
+                    let host = target.host.clone();
+                    let resolve = resolve.with_family(family);
+                    tokio::spawn(async move { network::resolve_host_ips(&host, &resolve).await })
+                })
+                .collect();
+            for lookup in lookups {
+                let ips = lookup.await.ok().and_then(Result::ok);
+                addresses.push(ips.map(|ips| ips.iter().map(|ip| ip.to_string()).collect()));
+            }
+        }
+        Self { targets, rows_per_host, addresses }
+    }
+
+    // add the rows of the targets and start pinging them
+    fn add(self, ip_data: &mut Vec<IpData>, manager: &mut PingTaskManager, resolve: &ResolveOptions) {
+        let Some(limit) = self.rows_per_host else {
+            // every target is resolved concurrently by its own task, unresolved ones keep retrying
+            for target in &self.targets {
+                add_target(ip_data, manager, resolve, target);
+            }
+            return;
+        };
+        let rows = self.targets.iter().flat_map(|target| resolve.families(&target.host).into_iter().map(move |family| (target, family)));
+        for ((target, family), addresses) in rows.zip(self.addresses) {
+            let row_family = resolve.dual_stack.then_some(family);
+            let Some(addresses) = addresses else {
+                // keep a single unresolved row that retries like any other target
+                let id = manager.next_id();
+                let mut data = IpData::for_target(id, target, String::new());
+                data.family = row_family;
+                ip_data.push(data);
+                manager.spawn_host(id, target, family);
+                continue;
+            };
+            for ip in addresses.iter().take(limit) {
+                let id = manager.next_id();
+                let mut data = IpData::for_target(id, target, ip.clone());
+                data.addresses = addresses.clone();
+                data.family = row_family;
+                manager.spawn(id, target, ip.clone());
+                ip_data.push(data);
+            }
+        }
+    }
+}
+
+// add the row of a target for each family and start pinging it, its task resolves it
+fn add_target(ip_data: &mut Vec<IpData>, manager: &mut PingTaskManager, resolve: &ResolveOptions, target: &TargetSpec) {
+    for family in resolve.families(&target.host) {
+        let id = manager.next_id();
+        let mut data = IpData::for_target(id, target, String::new());
+        data.family = resolve.dual_stack.then_some(family);
+        ip_data.push(data);
+        manager.spawn_host(id, target, family);
+    }
+}
+
+// apply a reloaded target file: targets no longer in it are stopped, the new ones in `added` are
+// added and unchanged ones keep their history. returns the hosts that now come from the file
+fn reload_targets(
+    ip_data: &mut Vec<IpData>,
+    manager: &mut PingTaskManager,
+    resolve: &ResolveOptions,
+    state: &mut AppState,
+    file_hosts: &HashSet<String>,
+    targets: &[TargetSpec],
+    added: NewTargets,
+) -> HashSet<String> {
+    for host in file_hosts.iter().filter(|host| !targets.iter().any(|target| target.host == **host)) {
+        while let Some(index) = ip_data.iter().position(|data| data.addr == *host) {
+            manager.remove(ip_data[index].id);
+            ip_data.remove(index);
+            state.target_removed(index, ip_data.len());
+        }
+    }
+
+    let added_hosts: HashSet<String> = added.targets.iter().map(|target| target.host.clone()).collect();
+    added.add(ip_data, manager, resolve);
+
+    let mut hosts = HashSet::new();
+    for target in targets {
+        if !added_hosts.contains(&target.host) && !file_hosts.contains(&target.host) {
+            // the target was given on the command line or added by hand, leave it alone
+            continue;
+        }
+        hosts.insert(target.host.clone());
+
+        for data in ip_data.iter_mut().filter(|data| data.addr == target.host) {
+            data.label = target.label.clone();
+            data.group = target.group.clone();
+            if data.options != target.options {
+                // restart probing with the new settings
+                data.options = target.options.clone();
+                manager.remove(data.id);
+                if data.ip.is_empty() {
+                    let family = data.family.unwrap_or_else(|| resolve.families(&target.host)[0]);
+                    manager.spawn_host(data.id, target, family);
+                } else {
+                    manager.spawn(data.id, target, data.ip.clone());
+                }
+            }
+        }
+    }
+    hosts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(argv: &str) -> Result<Args, clap::Error> {
+        parse_args(argv.split_whitespace()).map(|(args, _)| args)
+    }
+
+    #[test]
+    fn parses_the_bare_form_as_ping() {
+        let args = parse("nping 1.1.1.1 8.8.8.8 -c 5 -v table").unwrap();
+        assert!(args.command.is_none());
+        assert_eq!(args.ping.target, ["1.1.1.1", "8.8.8.8"]);
+        assert_eq!(args.ping.count, 5);
+        assert_eq!(args.global.view_type, "table");
+    }
+
+    #[test]
+    fn parses_ping() {
+        let args = parse("nping ping 1.1.1.1 --record session.jsonl -v point").unwrap();
+        let Some(Commands::Ping(ping)) = args.command else { panic!("not ping: {:?}", args.command) };
+        assert_eq!(ping.target, ["1.1.1.1"]);
+        assert_eq!(ping.record, Some(PathBuf::from("session.jsonl")));
+        assert_eq!(args.global.view_type, "point");
+
+        // a host named like a command
+        let args = parse("nping ping report").unwrap();
+        let Some(Commands::Ping(ping)) = args.command else { panic!("not ping: {:?}", args.command) };
+        assert_eq!(ping.target, ["report"]);
+    }
+
+    #[test]
+    fn address_families_conflict() {
+        assert!(parse("nping -4 -6 1.1.1.1").is_err());
+        assert!(parse("nping -4 --dual-stack 1.1.1.1").is_err());
+        assert!(parse("nping -6 --dual-stack 1.1.1.1").is_err());
+        let args = parse("nping -4 1.1.1.1").unwrap();
+        assert!(args.ping.force_ipv4 && !args.ping.force_ipv6 && !args.ping.dual_stack);
+    }
+
+    #[test]
+    fn parses_the_recording_commands() {
+        let args = parse("nping replay session.jsonl -v table --warn-rtt 50ms").unwrap();
+        assert!(matches!(args.command, Some(Commands::Replay { ref file }) if file == Path::new("session.jsonl")));
+        assert_eq!(args.global.view_type, "table");
+        assert_eq!(args.global.warn_rtt, Some(50.0));
+
+        let args = parse("nping report session.jsonl").unwrap();
+        assert!(matches!(args.command, Some(Commands::Report { ref file }) if file == Path::new("session.jsonl")));
+
+        let args = parse("nping compare before.jsonl after.jsonl --crit-loss 10%").unwrap();
+        let Some(Commands::Compare { before, after }) = args.command else { panic!("not compare: {:?}", args.command) };
+        assert_eq!((before, after), (PathBuf::from("before.jsonl"), PathBuf::from("after.jsonl")));
+        assert_eq!(args.global.crit_loss, Some(10.0));
+    }
+
+    #[test]
+    fn rejects_ping_options_of_other_commands() {
+        let err = parse("nping --record session.jsonl report f").unwrap_err();
+        assert!(err.to_string().contains("--record <RECORD> is an option of ping"), "{}", err);
+        assert!(parse("nping -c 5 replay f").is_err());
+        assert!(parse("nping report f --record session.jsonl").is_err());
+        assert!(parse("nping 127.0.0.1 report f").is_err());
+        assert!(parse("nping compare before.jsonl").is_err());
+    }
+
+    #[test]
+    fn global_options_go_before_or_after_a_command() {
+        for argv in ["nping -v table --config nping.toml report f", "nping report f -v table --config nping.toml"] {
+            let args = parse(argv).unwrap();
+            assert!(matches!(args.command, Some(Commands::Report { .. })), "{}", argv);
+            assert_eq!(args.global.view_type, "table");
+            assert_eq!(args.global.config, Some(PathBuf::from("nping.toml")));
+        }
+    }
+
+    #[test]
+    fn added_targets_are_checked_and_expanded() {
+        let none = TargetOptions::default();
+        let hosts = |targets: Vec<TargetSpec>| targets.into_iter().map(|target| target.host).collect::<Vec<_>>();
+        assert_eq!(hosts(parse_targets(&["10.0.0.0/30".to_string()], None, &none).unwrap()), ["10.0.0.1", "10.0.0.2"]);
+        assert_eq!(hosts(parse_targets(&["10.0.0.1-3".to_string()], None, &none).unwrap()), ["10.0.0.1", "10.0.0.2", "10.0.0.3"]);
+        assert!(parse_targets(&["sim://lat=bad".to_string()], None, &none).unwrap_err().starts_with("sim://lat=bad: "));
+
+        let targets = parse_targets(&["10.0.0.1".to_string(), "lab:10.0.0.2".to_string()], Some("wan"), &none).unwrap();
+        assert_eq!(targets[0].group.as_deref(), Some("wan"));
+        assert_eq!(targets[1].group.as_deref(), Some("lab"));
+    }
+
+    #[test]
+    fn targets_without_a_probe_get_the_one_of_the_command_line() {
+        let probe = TargetOptions { probe: Some(ProbeKind::Tcp), port: Some(443), ..TargetOptions::default() };
+        let mut dns = TargetSpec::new("10.0.0.53");
+        dns.options.probe = Some(ProbeKind::Dns);
+        let targets = with_probe(vec![TargetSpec::new("10.0.0.1"), dns.clone()], &probe);
+        assert_eq!((targets[0].options.probe, targets[0].options.port), (Some(ProbeKind::Tcp), Some(443)));
+        assert_eq!(targets[1], dns);
+    }
+
+    #[test]
+    fn a_session_without_probes_fails_with_its_errors() {
+        let mut errs = ErrorLog::default();
+        errs.push(Some(0), "127.0.0.1", ErrorKind::Ping, "127.0.0.1 ping err: Operation not permitted (os error 1)");
+        errs.push(None, "target file", ErrorKind::Target, "unknown target option: color");
+        errs.push(Some(1), "10.0.0.1", ErrorKind::Stopped, "stopped, the probes of the target ended");
+        assert_eq!(
+            stop_errors(&errs),
+            [
+                "127.0.0.1: 127.0.0.1 ping err: Operation not permitted (os error 1)",
+                "10.0.0.1: stopped, the probes of the target ended",
+            ]
+        );
+
+        let mut ip_data = vec![IpData::new(0, "127.0.0.1".to_string(), "127.0.0.1".to_string())];
+        assert_eq!(probed(&ip_data).unwrap_err(), "no target could be probed");
+        ip_data[0].update_timeout(10, Local::now());
+        assert!(probed(&ip_data).is_ok());
+    }
+
+    #[test]
+    fn help_lists_the_ping_options_under_ping() {
+        let help = cli().render_help().to_string();
+        assert!(help.contains("--view-type"));
+        assert!(help.contains("[TARGET]..."));
+        assert!(!help.contains("--count"));
+
+        let mut cli = cli();
+        let help = cli.find_subcommand_mut("ping").unwrap().render_help().to_string();
+        assert!(help.contains("--count"));
+    }
+}
@@ -1,221 +1,508 @@
-use std::collections::VecDeque;
-use std::error::Error;
-use std::net::{IpAddr, ToSocketAddrs};
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::fmt;
+use std::str::FromStr;
 use std::sync::{Arc, Mutex};
 use std::sync::mpsc::SyncSender;
-use std::time::Duration;
+use tokio::runtime::Handle;
+use tokio::sync::mpsc::Receiver;
+use tokio::task::JoinHandle;
+use std::time::{Duration, Instant};
 use anyhow::{anyhow, Context};
+use hickory_resolver::config::{LookupIpStrategy, NameServerConfigGroup, ResolverConfig, ResolverOpts};
+use hickory_resolver::TokioAsyncResolver;
+use serde::{Deserialize, Serialize};
 
-use crate::pinger::{ping, PingOptions, PingResult};
-use crate::ip_data::IpData;
+use crate::pinger::{ping_with, PingOptions, PingResult, Prober};
+use crate::probe::{is_simulated, probe_kind, prober_for};
+use crate::error_log::{ErrorKind, ErrorLog};
+use crate::target::{TargetOptions, TargetSpec};
 
-// get host ip address default to ipv4
-pub(crate) fn resolve_host_ips(host: &str, force_ipv6: bool) -> Result<Vec<IpAddr>, Box<dyn Error>> {
+/// how long to wait before resolving an unresolved target again
+pub const RESOLVE_RETRY_INTERVAL: Duration = Duration::from_secs(5);
+
+/// which of the addresses a hostname resolves to are pinged
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AddressPick {
+    /// the first address
+    #[default]
+    First,
+    /// every address, one row each
+    All,
+    /// the address at this position of the answer
+    Index(usize),
+}
+
+impl FromStr for AddressPick {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "first" => Ok(AddressPick::First),
+            "all" => Ok(AddressPick::All),
+            _ => value
+                .parse()
+                .map(AddressPick::Index)
+                .map_err(|_| format!("expected first, all or an index, got: {}", value)),
+        }
+    }
+}
+
+/// address family of a ping row
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum IpFamily {
+    /// ipv4
+    V4,
+    /// ipv6
+    V6,
+}
+
+impl IpFamily {
+    pub(crate) fn of(ip: &IpAddr) -> Self {
+        match ip {
+            IpAddr::V4(_) => IpFamily::V4,
+            IpAddr::V6(_) => IpFamily::V6,
+        }
+    }
+
+    pub(crate) fn matches(&self, ip: &IpAddr) -> bool {
+        IpFamily::of(ip) == *self
+    }
+}
+
+impl fmt::Display for IpFamily {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IpFamily::V4 => write!(f, "v4"),
+            IpFamily::V6 => write!(f, "v6"),
+        }
+    }
+}
+
+/// how target hostnames are resolved
+#[derive(Debug, Clone)]
+pub struct ResolveOptions {
+    /// the family hostnames are resolved to
+    pub family: IpFamily,
+    /// ping every hostname over both ipv4 and ipv6, one row each
+    pub dual_stack: bool,
+    /// give up resolving a name after this long
+    pub timeout: Duration,
+    /// ask this server instead of the system resolver
+    pub dns_server: Option<SocketAddr>,
+    /// which of the resolved addresses are pinged
+    pub pick: AddressPick,
+}
+
+impl ResolveOptions {
+    /// the families a target gets a row for, an ip address only has its own
+    pub fn families(&self, host: &str) -> Vec<IpFamily> {
+        match host.parse::<IpAddr>() {
+            Ok(ip) => vec![IpFamily::of(&ip)],
+            // a simulated target has no address to get for each family
+            Err(_) if self.dual_stack && !is_simulated(host) => vec![IpFamily::V4, IpFamily::V6],
+            Err(_) => vec![self.family],
+        }
+    }
+
+    /// the same options resolving to another family
+    pub fn with_family(&self, family: IpFamily) -> Self {
+        Self { family, ..self.clone() }
+    }
+
+    /// the address a target probes out of the ones its host resolved to.
+    /// with an index the address at that position is followed, otherwise
+    /// the current address is kept as long as the host still resolves to it
+    pub fn pick_address(&self, host: &str, addresses: &[String], current: &str) -> Result<String, String> {
+        match self.pick {
+            AddressPick::Index(index) => addresses.get(index).cloned().ok_or_else(|| {
+                format!("host {} resolved to {} addresses, no address at index {}", host, addresses.len(), index)
+            }),
+            _ if addresses.iter().any(|ip| ip == current) => Ok(current.to_string()),
+            _ => Ok(addresses[0].clone()),
+        }
+    }
+}
+
+/// parse a dns server given as ip or ip:port, ipv6 with a port is written as `[ip]:port`
+pub fn parse_dns_server(value: &str) -> Result<SocketAddr, String> {
+    if let Ok(addr) = value.parse::<SocketAddr>() {
+        return Ok(addr);
+    }
+    value
+        .parse::<IpAddr>()
+        .map(|ip| SocketAddr::new(ip, 53))
+        .map_err(|_| format!("invalid dns server: {}", value))
+}
+
+// query a specific dns server for the A and AAAA records of a host
+async fn lookup_with_server(host: &str, server: SocketAddr) -> anyhow::Result<Vec<IpAddr>> {
+    let name_servers = NameServerConfigGroup::from_ips_clear(&[server.ip()], server.port(), true);
+    let config = ResolverConfig::from_parts(None, vec![], name_servers);
+    let mut opts = ResolverOpts::default();
+    opts.ip_strategy = LookupIpStrategy::Ipv4AndIpv6;
+    // always ask the server, the answer itself is what is being looked at
+    opts.cache_size = 0;
+    let resolver = TokioAsyncResolver::tokio(config, opts);
+    let lookup = resolver
+        .lookup_ip(host)
+        .await
+        .with_context(|| format!("failed to resolve host: {} via {}", host, server))?;
+    Ok(lookup.iter().collect())
+}
+
+/// the addresses of a host in the family of the options, giving up after the timeout
+pub async fn resolve_host_ips(host: &str, options: &ResolveOptions) -> anyhow::Result<Vec<IpAddr>> {
+    let lookup = async {
+        match options.dns_server {
+            Some(server) => lookup_with_server(host, server).await,
+            None => Ok(tokio::net::lookup_host((host, 80))
+                .await
+                .with_context(|| format!("failed to resolve host: {}", host))?
+                .map(|s| s.ip())
+                .collect()),
+        }
+    };
 
     // get ip address
-    let ipaddr: Vec<_> = (host, 80)
-        .to_socket_addrs()
-        .with_context(|| format!("failed to resolve host: {}", host))?
-        .map(|s| s.ip())
-        .collect();
+    let ipaddr: Vec<IpAddr> = tokio::time::timeout(options.timeout, lookup)
+        .await
+        .map_err(|_| anyhow!("timed out after {:?} resolving host: {}", options.timeout, host))??;
 
     if ipaddr.is_empty() {
-        return Err(anyhow!("Could not resolve host: {}", host).into());
+        return Err(anyhow!("Could not resolve host: {}", host));
     }
 
     // filter ipv4 or ipv6
-    let filtered_ips: Vec<IpAddr> = if force_ipv6 {
-        ipaddr.into_iter()
-            .filter(|ip| matches!(ip, IpAddr::V6(_)))
-            .collect()
-    } else {
-        ipaddr.into_iter()
-            .filter(|ip| matches!(ip, IpAddr::V4(_)))
-            .collect()
-    };
+    let filtered_ips: Vec<IpAddr> = ipaddr.into_iter()
+        .filter(|ip| options.family.matches(ip))
+        .collect();
 
     if filtered_ips.is_empty() {
-        return Err(anyhow!("Could not resolve host: {}", host).into());
+        return Err(anyhow!("Could not resolve host: {} to an ip{} address", host, options.family));
     }
 
     Ok(filtered_ips)
 }
 
-pub(crate) fn get_host_ipaddr(host: &str, force_ipv6: bool) -> Result<String, Box<dyn Error>> {
-    let ips = resolve_host_ips(host, force_ipv6)?;
-    Ok(ips[0].to_string())
+// resolve a host with the settings of the ping context, as strings
+async fn resolve_host(host: &str, ctx: &PingContext) -> Result<Vec<String>, String> {
+    resolve_host_ips(host, &ctx.resolve)
+        .await
+        .map(|ips| ips.iter().map(|ip| ip.to_string()).collect())
+        .map_err(|err| err.to_string())
+}
+
+/// a single probe result of a target, sent from the ping task to the ui
+#[derive(Debug, Clone)]
+pub enum PingEvent {
+    /// a reply was received
+    Pong {
+        /// id of the target
+        id: usize,
+        /// rtt in milliseconds
+        rtt: f64,
+    },
+    /// no reply was received in time
+    Timeout {
+        /// id of the target
+        id: usize,
+    },
+    /// the target was (re-)resolved, probing continues on `ip`
+    Resolved {
+        /// id of the target
+        id: usize,
+        /// the address that is probed
+        ip: String,
+        /// every address the target resolved to
+        addresses: Vec<String>,
+    },
+    /// the target could not be resolved yet, resolving is retried
+    Unresolved {
+        /// id of the target
+        id: usize,
+    },
+}
+
+/// settings and shared handles every ping task is started with
+#[derive(Clone)]
+pub struct PingContext {
+    /// probes sent to each target
+    pub count: usize,
+    /// milliseconds between two probes of a target without its own interval
+    pub interval: u64,
+    /// cleared to stop every task
+    pub running: Arc<Mutex<bool>>,
+    /// set to hold every task without stopping it
+    pub paused: Arc<Mutex<bool>>,
+    /// how targets are resolved
+    pub resolve: ResolveOptions,
+    /// re-resolve the target periodically and follow address changes
+    pub resolve_every: Option<Duration>,
+    /// the errors of every task go here
+    pub errs: Arc<Mutex<ErrorLog>>,
+    /// the results of every task are sent here
+    pub ping_update_tx: Arc<SyncSender<PingEvent>>,
 }
 
-pub(crate) fn get_multiple_host_ipaddr(host: &str, force_ipv6: bool, multiple: usize) -> Result<Vec<String>, Box<dyn Error>> {
-    let ips = resolve_host_ips(host, force_ipv6)?;
-    Ok(ips.into_iter()
-        .take(multiple)
-        .map(|ip| ip.to_string())
-        .collect())
+impl PingContext {
+    /// how long an unresolved target waits before it is resolved again, a long --resolve-every
+    /// must not leave a name that failed once unresolved for that long
+    pub fn resolve_retry_interval(&self) -> Duration {
+        self.resolve_every.map_or(RESOLVE_RETRY_INTERVAL, |every| every.min(RESOLVE_RETRY_INTERVAL))
+    }
 }
 
+/// makes the prober of a ping task, again whenever the task starts probing anew
+pub type MakeProber = Box<dyn Fn(&PingOptions) -> anyhow::Result<Box<dyn Prober>> + Send + Sync>;
+
+// the prober the settings of the target select
+fn target_prober(host: &str, options: &TargetOptions) -> MakeProber {
+    let host = host.to_string();
+    let options = options.clone();
+    Box::new(move |ping_options| prober_for(&options, &host, ping_options))
+}
 
-pub struct PingTask {
+struct PingTask {
+    id: usize,
     addr: String,
     ip: String,
-    count: usize,
-    interval: u64,
-    running: Arc<Mutex<bool>>,
-    errs: Arc<Mutex<Vec<String>>>,
-    point_num: i32,
+    /// the settings of the target, selecting how it is probed
+    options: TargetOptions,
+    ctx: PingContext,
+    make_prober: MakeProber,
 }
 
 impl PingTask {
-    pub fn new(
-        addr: String,
-        ip: String,
-        count: usize,
-        interval: u64,
-        running: Arc<Mutex<bool>>,
-        errs: Arc<Mutex<Vec<String>>>,
-        point_num: i32,
-    ) -> Self {
-        Self {
-            addr,
-            ip,
-            count,
-            interval,
-            running,
-            errs,
-            point_num,
-        }
+    fn new(id: usize, addr: String, ip: String, options: TargetOptions, ctx: PingContext, make_prober: MakeProber) -> Self {
+        Self { id, addr, ip, options, ctx, make_prober }
     }
 
-    pub async fn run(&self, ping_update_tx: Arc<SyncSender<IpData>>) -> Result<(), Box<dyn Error>>
-    {
-        let mut ip_data = IpData {
-            addr: self.addr.clone(),
-            ip: self.ip.clone(),
-            rtts: VecDeque::new(),
-            last_attr: 0.0,
-            min_rtt: 0.0,
-            max_rtt: 0.0,
-            timeout: 0,
-            received: 0,
-            pop_count: 0,
-        };
+    fn set_error(&self, kind: ErrorKind, err: String) {
+        set_error(&self.ctx.errs, Some(self.id), &self.addr, kind, err);
+    }
+
+    async fn run(&self) {
         // interval defined 0.5s/every ping
-        let interval = Duration::from_millis(self.interval);
-        let options = PingOptions::new(
-            self.ip.clone(),
-            interval,
-            None,
-        );
+        let interval = Duration::from_millis(self.ctx.interval);
+        let mut ip = self.ip.clone();
+        let mut last_resolve = Instant::now();
 
-        // star ping
-        let mut stream = ping(options).await?;
+        // the stream is dropped while paused, so no probes are sent
+        let mut stream: Option<Receiver<PingResult>> = None;
+        let mut sent = 0;
 
-        for _ in 0..self.count {
+        while sent < self.ctx.count {
             // if ctrl+c is pressed, break the loop
-            if !*self.running.lock().unwrap() {
+            if !*self.ctx.running.lock().unwrap() {
                 break;
             }
-            match stream.recv().await {
+            if *self.ctx.paused.lock().unwrap() {
+                stream = None;
+                tokio::time::sleep(Duration::from_millis(100)).await;
+                continue;
+            }
+            if let Some(resolve_every) = self.ctx.resolve_every.filter(|_| self.probe_resolves()) {
+                if last_resolve.elapsed() >= resolve_every {
+                    last_resolve = Instant::now();
+                    match resolve_host(&self.addr, &self.ctx).await {
+                        Ok(addresses) => {
+                            match self.ctx.resolve.pick_address(&self.addr, &addresses, &ip) {
+                                Ok(new_ip) if new_ip != ip => {
+                                    ip = new_ip;
+                                    stream = None;
+                                }
+                                Ok(_) => {}
+                                Err(err) => self.set_error(ErrorKind::Resolve, err),
+                            }
+                            let event = PingEvent::Resolved { id: self.id, ip: ip.clone(), addresses };
+                            if self.ctx.ping_update_tx.send(event).is_err() {
+                                break;
+                            }
+                        }
+                        Err(err) => self.set_error(ErrorKind::Resolve, err),
+                    }
+                }
+            }
+            if stream.is_none() {
+                // star ping with the backend of the target
+                let mut options = PingOptions::new(
+                    ip.clone(),
+                    interval,
+                    None,
+                );
+                // a target that stops replying is probed on, so it can come back up
+                options.stop_after_lost_n = 0;
+                let prober = match (self.make_prober)(&options) {
+                    Ok(prober) => prober,
+                    Err(err) => {
+                        // like a raw socket without the permission for it, retrying will not help
+                        self.set_error(ErrorKind::Ping, format!("{} ping err: {:#}", ip, err));
+                        break;
+                    }
+                };
+                stream = Some(ping_with(options, prober));
+            }
+            sent += 1;
+
+            let event = match stream.as_mut().unwrap().recv().await {
                 Some(result) => {
                     match result {
                         PingResult::Pong(duration, _size) => {
                             // calculate rtt
                             let rtt = duration.as_secs_f64() * 1000.0;
                             let rtt_display: f64 = format!("{:.2}", rtt).parse().unwrap();
-                            update_stats(
-                                &mut ip_data,
-                                self.ip.parse().unwrap(),
-                                rtt_display,
-                                self.point_num,
-                            );
+                            PingEvent::Pong { id: self.id, rtt: rtt_display }
                         }
                         PingResult::Timeout(_) => {
-                            update_timeout_stats(&mut ip_data, self.ip.parse().unwrap(), self.point_num);
+                            PingEvent::Timeout { id: self.id }
                         }
                         PingResult::PingExited(status, err) => {
-                            if status.code() != Option::from(0) {
-                                let err = format!("host({}) ping err, reason: ping excited, status: {} err: {}", self.ip, err, status);
-                                set_error(self.errs.clone(), err);
+                            if status.code() != Some(0) {
+                                self.set_error(ErrorKind::Ping, format!("{} ping exited, status: {} err: {}", ip, status, err));
                             }
+                            continue;
                         }
                         PingResult::Unknown(msg) => {
-                            let err = format!("host({}) ping err, reason:unknown, err: {}", self.ip, msg);
-                            set_error(self.errs.clone(), err);
+                            // a probe that failed, like a refused connection, is lost as well
+                            self.set_error(ErrorKind::Ping, format!("{} ping err: {}", ip, msg));
+                            PingEvent::Timeout { id: self.id }
                         }
                     }
                 }
                 None => {
-                    // end of stream
-                    set_error(self.errs.clone(),"ping completed".into());
+                    // the probes only end when the prober failed
+                    self.set_error(ErrorKind::Stopped, "stopped, the probes of the target ended".into());
                     break;
                 }
+            };
+
+            // send ping data to update, stop when the ui has exited
+            if self.ctx.ping_update_tx.send(event).is_err() {
+                break;
             }
+        }
+    }
 
+    fn probe_resolves(&self) -> bool {
+        probe_kind(&self.options, &self.addr).resolves()
+    }
+}
+
+/// spawns and cancels the ping task of every target, targets can be added and removed at runtime
+pub struct PingTaskManager {
+    runtime: Handle,
+    ctx: PingContext,
+    next_id: usize,
+    tasks: HashMap<usize, JoinHandle<()>>,
+}
 
-            // send ping data to update
-            ping_update_tx.send(ip_data.clone())?;
+impl PingTaskManager {
+    /// spawn tasks on the runtime with the context
+    pub fn new(runtime: Handle, ctx: PingContext) -> Self {
+        Self {
+            runtime,
+            ctx,
+            next_id: 0,
+            tasks: HashMap::new(),
         }
+    }
 
-        Ok(())
+    /// reserve an id for a new target
+    pub fn next_id(&mut self) -> usize {
+        self.next_id += 1;
+        self.next_id
+    }
+
+    // the context of a task resolving its host to one family, with the settings of the target
+    fn context_for(&self, target: &TargetSpec, family: IpFamily) -> PingContext {
+        let mut ctx = self.ctx.clone();
+        ctx.resolve = ctx.resolve.with_family(family);
+        if let Some(interval) = target.options.interval {
+            ctx.interval = interval.as_millis() as u64;
+        }
+        ctx
+    }
+
+    /// start pinging an already resolved ip of the target
+    pub fn spawn(&mut self, id: usize, target: &TargetSpec, ip: String) {
+        self.spawn_with(id, target, ip, target_prober(&target.host, &target.options));
+    }
+
+    /// start pinging an already resolved ip of the target through the probers `make_prober` makes,
+    /// like a `MockProber` in tests
+    pub fn spawn_with(&mut self, id: usize, target: &TargetSpec, ip: String, make_prober: MakeProber) {
+        let family = ip.parse::<IpAddr>().map(|ip| IpFamily::of(&ip)).unwrap_or(self.ctx.resolve.family);
+        let task = PingTask::new(id, target.host.clone(), ip, target.options.clone(), self.context_for(target, family), make_prober);
+        let handle = self.runtime.spawn(async move {
+            task.run().await;
+        });
+        self.tasks.insert(id, handle);
+    }
+
+    /// resolve the host to the family in the background, retrying until it succeeds, then start pinging it
+    pub fn spawn_host(&mut self, id: usize, target: &TargetSpec, family: IpFamily) {
+        let ctx = self.context_for(target, family);
+        let host = target.host.clone();
+        let options = target.options.clone();
+        let kind = probe_kind(&options, &host);
+        let handle = self.runtime.spawn(async move {
+            let mut last_err = String::new();
+            let (ip, addresses) = loop {
+                // a backend that does not need an address is shown by its name instead
+                if !kind.resolves() {
+                    break (kind.to_string(), Vec::new());
+                }
+                let resolved = resolve_host(&host, &ctx).await.and_then(|addresses| {
+                    let ip = ctx.resolve.pick_address(&host, &addresses, "")?;
+                    Ok((ip, addresses))
+                });
+                match resolved {
+                    Ok(resolved) => break resolved,
+                    Err(err) => {
+                        // only log when the reason changes, the row shows the target is unresolved
+                        if err != last_err {
+                            set_error(&ctx.errs, Some(id), &host, ErrorKind::Resolve, err.clone());
+                            last_err = err;
+                        }
+                        if ctx.ping_update_tx.send(PingEvent::Unresolved { id }).is_err() {
+                            return;
+                        }
+                    }
+                }
+                tokio::time::sleep(ctx.resolve_retry_interval()).await;
+                if !*ctx.running.lock().unwrap() {
+                    return;
+                }
+            };
+            let event = PingEvent::Resolved {
+                id,
+                ip: ip.clone(),
+                addresses,
+            };
+            if ctx.ping_update_tx.send(event).is_err() {
+                return;
+            }
+            let make_prober = target_prober(&host, &options);
+            PingTask::new(id, host, ip, options, ctx, make_prober).run().await;
+        });
+        self.tasks.insert(id, handle);
+    }
+
+    /// stop the ping task of a target
+    pub fn remove(&mut self, id: usize) {
+        if let Some(handle) = self.tasks.remove(&id) {
+            handle.abort();
+        }
+    }
+
+    /// true when there are tasks and all of them have completed their count
+    pub fn all_finished(&self) -> bool {
+        !self.tasks.is_empty() && self.tasks.values().all(|task| task.is_finished())
     }
 }
 
-// send ping to the target address
-pub async fn send_ping(
-    addr: String,
-    ip: String,
-    errs: Arc<Mutex<Vec<String>>>,
-    count: usize,
-    interval: i32,
-    running: Arc<Mutex<bool>>,
-    ping_update_tx: Arc<SyncSender<IpData>>,
-    point_num: i32,
-) -> Result<(), Box<dyn Error>>
-{
-    // draw ui first
-    let task = PingTask::new(
-        addr.to_string(),
-        ip,
-        count,
-        interval as u64,
-        running,
-        errs,
-        point_num,
-    );
-    Ok(task.run(ping_update_tx).await?)
-}
-
-// update statistics
-fn update_stats(ip_data: &mut IpData, ip: IpAddr, rtt: f64, point_num: i32) {
-    ip_data.ip = ip.to_string();
-    ip_data.received += 1;
-    ip_data.last_attr = rtt;
-    ip_data.rtts.push_back(rtt);
-    if ip_data.min_rtt == 0.0 || rtt < ip_data.min_rtt {
-        ip_data.min_rtt = rtt;
-    }
-    if rtt > ip_data.max_rtt {
-        ip_data.max_rtt = rtt;
-    }
-    if ip_data.rtts.len() > point_num as usize {
-        ip_data.rtts.pop_front();
-        ip_data.pop_count += 1;
-    }
-}
-
-// update timeout statistics
-fn update_timeout_stats(ip_data: &mut IpData, ip: IpAddr, point_num: i32) {
-    ip_data.rtts.push_back(-1.0);
-    ip_data.last_attr = -1.0;
-    ip_data.ip = ip.to_string();
-    ip_data.timeout += 1;
-    if ip_data.rtts.len() > point_num as usize {
-        ip_data.rtts.pop_front();
-        ip_data.pop_count += 1;
-    }
-}
-
-fn set_error(errs: Arc<Mutex<Vec<String>>>, err: String) {
-    let mut err_list = errs.lock().unwrap();
-    err_list.push(err)
-}
\ No newline at end of file
+fn set_error(errs: &Arc<Mutex<ErrorLog>>, id: Option<usize>, target: &str, kind: ErrorKind, err: String) {
+    errs.lock().unwrap().push(id, target, kind, err);
+}
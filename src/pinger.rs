@@ -1,3 +1,5 @@
+use std::future::Future;
+use std::pin::Pin;
 use std::process::ExitStatus;
 use std::time::Duration;
 use surge_ping::SurgeError;
@@ -6,23 +8,34 @@ use tokio::task;
 
 use anyhow::Result;
 
+/// what to ping and how often
 #[derive(Debug, Clone)]
 pub struct PingOptions {
+    /// the host or address that is pinged
     pub target: String,
+    /// time between two probes
     pub interval: Duration,
+    /// send the probes from this interface
     pub interface: Option<String>,
+    /// stop after this many timeouts in a row, 0 keeps probing until the receiver is dropped
     pub stop_after_lost_n : i32,
 }
 
+/// the outcome of a single probe, each with the address that was probed
 #[derive(Debug)]
 pub enum PingResult {
+    /// a reply arrived after the duration
     Pong(Duration, String),
+    /// no reply arrived in time
     Timeout(String),
+    /// the probe could not be sent, with the reason
     Unknown(String),
+    /// pinging stopped, with the reason
     PingExited(ExitStatus, String),
 }
 
 impl PingOptions {
+    /// ping a target every interval, stopping after 10 timeouts in a row
     pub fn new(target: impl ToString, interval: Duration, interface: Option<String>) -> Self {
         let target = target.to_string();
         Self {
@@ -34,12 +47,107 @@ impl PingOptions {
     }
 }
 
-async fn resolve_target(target: &String) -> Result<std::net::IpAddr> {
+/// the reply of a single probe, resolved by the backend that sent it
+pub type ProbeFuture<'a> = Pin<Box<dyn Future<Output = PingResult> + Send + 'a>>;
+
+/// a way of probing a target, `ping_with` sends one probe after another through it
+pub trait Prober: Send {
+    /// send probe number `seq` and wait for its reply or timeout
+    fn probe(&mut self, seq: u16) -> ProbeFuture<'_>;
+}
+
+impl<P: Prober + ?Sized> Prober for Box<P> {
+    fn probe(&mut self, seq: u16) -> ProbeFuture<'_> {
+        (**self).probe(seq)
+    }
+}
+
+/// icmp echo requests, the target is resolved on the first probe
+pub struct IcmpProber {
+    target: String,
+    // client object must keep alive while pinging
+    client: surge_ping::Client,
+    pinger: Option<surge_ping::Pinger>,
+    payload: Vec<u8>,
+}
+
+impl IcmpProber {
+    /// open the icmp socket, this needs raw socket privileges
+    pub fn new(options: &PingOptions) -> Result<Self> {
+        let mut cfg = surge_ping::Config::builder();
+        // ipv6 targets need an icmpv6 socket
+        if let Ok(std::net::IpAddr::V6(_)) = options.target.parse::<std::net::IpAddr>() {
+            cfg = cfg.kind(surge_ping::ICMP::V6);
+        }
+        let client = surge_ping::Client::new(&cfg.build())?;
+        Ok(Self {
+            target: options.target.clone(),
+            client,
+            pinger: None,
+            payload: vec![0; 8],
+        })
+    }
+}
+
+impl Prober for IcmpProber {
+    fn probe(&mut self, seq: u16) -> ProbeFuture<'_> {
+        Box::pin(async move {
+            if self.pinger.is_none() {
+                let Ok(addr) = resolve_target(&self.target).await else {
+                    return PingResult::Unknown("Could not resolve hostname".to_string());
+                };
+                let id = surge_ping::PingIdentifier(rand::random::<u16>());
+                self.pinger = Some(self.client.pinger(addr, id).await);
+            }
+            let pinger = self.pinger.as_mut().unwrap();
+            match pinger.ping(surge_ping::PingSequence(seq), &self.payload).await {
+                Ok((_, rtt)) => PingResult::Pong(rtt, self.target.clone()),
+                Err(SurgeError::Timeout { seq: _ }) => PingResult::Timeout(self.target.clone()),
+                Err(err) => PingResult::Unknown(err.to_string()),
+            }
+        })
+    }
+}
+
+/// replies with scripted results instead of sending anything, for testing without raw sockets.
+/// the replies are repeated from the start once all of them were used
+#[derive(Debug, Clone)]
+pub struct MockProber {
+    target: String,
+    /// the rtt of each probe, None for a timeout
+    replies: Vec<Option<Duration>>,
+    next: usize,
+}
+
+impl MockProber {
+    /// answer the probes with the replies in order, None is a timeout
+    pub fn new(target: impl ToString, replies: impl IntoIterator<Item = Option<Duration>>) -> Self {
+        Self {
+            target: target.to_string(),
+            replies: replies.into_iter().collect(),
+            next: 0,
+        }
+    }
+}
+
+impl Prober for MockProber {
+    fn probe(&mut self, _seq: u16) -> ProbeFuture<'_> {
+        let reply = self.replies.get(self.next % self.replies.len().max(1)).copied().flatten();
+        self.next += 1;
+        let result = match reply {
+            Some(rtt) => PingResult::Pong(rtt, self.target.clone()),
+            None => PingResult::Timeout(self.target.clone()),
+        };
+        Box::pin(async move { result })
+    }
+}
+
+async fn resolve_target(target: &str) -> Result<std::net::IpAddr> {
     if let Ok(addr) = target.parse::<std::net::IpAddr>() {
         Ok(addr)
     } else {
         // if the target is a hostname, resolve it to an IP address
-        let mut ips = tokio::net::lookup_host(&target).await?;
+        let mut ips = tokio::net::lookup_host((target, 0)).await?;
         ips.next()
             .map(|addr| addr.ip())
             .ok_or(anyhow::anyhow!("Could not resolve hostname"))
@@ -48,67 +156,35 @@ async fn resolve_target(target: &String) -> Result<std::net::IpAddr> {
 
 /// Start pinging a an address. The address can be either a hostname or an IP address.
 pub async fn ping(options: PingOptions) -> Result<mpsc::Receiver<PingResult>> {
-    let (tx, rx) = mpsc::channel::<PingResult>(1);
-    let cfg = surge_ping::Config::builder().build();
+    let prober = IcmpProber::new(&options)?;
+    Ok(ping_with(options, prober))
+}
 
-    // client object must keep alive while pinging
-    let client = surge_ping::Client::new(&cfg)?;
+/// Start probing the target of the options through a backend, the results are sent in order
+/// until the receiver is dropped or `stop_after_lost_n` probes in a row were lost, if it is set.
+pub fn ping_with(options: PingOptions, mut prober: impl Prober + 'static) -> mpsc::Receiver<PingResult> {
+    let (tx, rx) = mpsc::channel::<PingResult>(1);
     task::spawn(async move {
-        let id = surge_ping::PingIdentifier(rand::random::<u16>());
-        // resolve host first
-        let target_addr: std::net::IpAddr;
-        loop {
-            if let Ok(addr) = resolve_target(&options.target).await {
-                target_addr = addr;
-                break;
-            } else {
-                if tx
-                    .send(PingResult::Unknown(
-                        "Could not resolve hostname".to_string(),
-                    ))
-                    .await
-                    .is_err()
-                {
-                    return;
-                }
-            }
-        }
-
-        // ping loop
-        let mut pinger = client.pinger(target_addr, id).await;
         let mut seq: u16 = 0;
-        let palyload: Vec<u8> = vec![0; 8];
         let mut lost_count = 0;
         loop {
-            seq += 1;
-            let prin_result = match pinger.ping(surge_ping::PingSequence(seq), &palyload).await {
-                Ok((_, rtt)) => {
-                    lost_count = 0;
-                    PingResult::Pong(rtt, options.target.clone())
-                }
-                Err(err) => match err {
-                    SurgeError::Timeout { seq: _ } => {
-                        lost_count += 1;
-                        PingResult::Timeout(options.target.clone())
-                    }
-                    _ => {
-                        lost_count += 1;
-                        PingResult::Unknown(err.to_string())
-                    }
-                }
-            };
+            seq = seq.wrapping_add(1);
+            let result = prober.probe(seq).await;
+            match result {
+                PingResult::Pong(..) => lost_count = 0,
+                PingResult::Timeout(_) | PingResult::Unknown(_) | PingResult::PingExited(..) => lost_count += 1,
+            }
             // send ping result
-            if tx.send(prin_result).await.is_err() {
+            if tx.send(result).await.is_err() {
                 // write channel failed
                 break;
             }
-            if lost_count >= options.stop_after_lost_n {
+            if options.stop_after_lost_n > 0 && lost_count >= options.stop_after_lost_n {
                 // stop pinging if lost count exceeds the limit
                 break;
             }
             tokio::time::sleep(options.interval).await;
         }
     });
-
-    Ok(rx)
+    rx
 }
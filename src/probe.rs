@@ -0,0 +1,340 @@
+use std::fmt;
+use std::net::{IpAddr, SocketAddr};
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+use anyhow::{anyhow, Context};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpStream, UdpSocket};
+use crate::pinger::{IcmpProber, PingOptions, PingResult, ProbeFuture, Prober};
+use crate::target::{parse_duration, parse_loss, TargetOptions};
+
+/// how long the tcp, udp, http and dns probes wait for a reply
+pub const PROBE_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// hosts like `sim://lat=20ms,jitter=5ms,loss=2%` are simulated with these settings
+pub const SIM_SCHEME: &str = "sim://";
+
+/// how a target is probed, set with `probe=` in a target file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ProbeKind {
+    /// icmp echo requests
+    #[default]
+    Icmp,
+    /// time to establish a tcp connection
+    Tcp,
+    /// time until a udp datagram is answered
+    Udp,
+    /// time until the status line of a `GET /` arrives
+    Http,
+    /// time until a dns server answers a query
+    Dns,
+    /// synthetic replies, nothing is sent
+    Sim,
+}
+
+impl ProbeKind {
+    /// every kind, in the order they are listed in errors
+    pub const ALL: [ProbeKind; 6] = [ProbeKind::Icmp, ProbeKind::Tcp, ProbeKind::Udp, ProbeKind::Http, ProbeKind::Dns, ProbeKind::Sim];
+
+    /// the port probed when the target does not give one, None when a port is required or meaningless
+    pub fn default_port(&self) -> Option<u16> {
+        match self {
+            ProbeKind::Http => Some(80),
+            ProbeKind::Dns => Some(53),
+            _ => None,
+        }
+    }
+
+    /// whether the probe goes to a port of the target
+    pub fn uses_port(&self) -> bool {
+        matches!(self, ProbeKind::Tcp | ProbeKind::Udp | ProbeKind::Http | ProbeKind::Dns)
+    }
+
+    /// whether the host is resolved to an address before probing
+    pub fn resolves(&self) -> bool {
+        *self != ProbeKind::Sim
+    }
+}
+
+impl fmt::Display for ProbeKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            ProbeKind::Icmp => "icmp",
+            ProbeKind::Tcp => "tcp",
+            ProbeKind::Udp => "udp",
+            ProbeKind::Http => "http",
+            ProbeKind::Dns => "dns",
+            ProbeKind::Sim => "sim",
+        };
+        f.pad(name)
+    }
+}
+
+impl FromStr for ProbeKind {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        ProbeKind::ALL
+            .into_iter()
+            .find(|kind| kind.to_string() == value)
+            .ok_or_else(|| {
+                let supported: Vec<String> = ProbeKind::ALL.iter().map(|kind| kind.to_string()).collect();
+                format!("unsupported probe type: {} (supported: {})", value, supported.join(", "))
+            })
+    }
+}
+
+/// whether the host is a `sim://` target
+pub fn is_simulated(host: &str) -> bool {
+    host.starts_with(SIM_SCHEME)
+}
+
+/// how a target is probed, a `sim://` host is always simulated
+pub fn probe_kind(target: &TargetOptions, host: &str) -> ProbeKind {
+    if is_simulated(host) {
+        ProbeKind::Sim
+    } else {
+        target.probe.unwrap_or_default()
+    }
+}
+
+/// the backend probing a target with its settings, `options.target` is the resolved address
+pub fn prober_for(target: &TargetOptions, host: &str, options: &PingOptions) -> anyhow::Result<Box<dyn Prober>> {
+    let kind = probe_kind(target, host);
+    if kind == ProbeKind::Icmp {
+        return Ok(Box::new(IcmpProber::new(options)?));
+    }
+    if kind == ProbeKind::Sim {
+        let sim = match host.strip_prefix(SIM_SCHEME) {
+            Some(settings) => settings.parse().map_err(|err: String| anyhow!(err))?,
+            None => SimOptions::default(),
+        };
+        return Ok(Box::new(SimProber::new(host, sim)));
+    }
+
+    let ip: IpAddr = options
+        .target
+        .parse()
+        .with_context(|| format!("{} probes need an ip address, got: {}", kind, options.target))?;
+    let port = target
+        .port
+        .or(kind.default_port())
+        .ok_or_else(|| anyhow!("{} probes need a port", kind))?;
+    let addr = SocketAddr::new(ip, port);
+    Ok(match kind {
+        ProbeKind::Tcp => Box::new(TcpProber { addr }),
+        ProbeKind::Udp => Box::new(UdpProber { addr }),
+        ProbeKind::Http => Box::new(HttpProber { addr, host: host.to_string() }),
+        _ => Box::new(DnsProber { addr }),
+    })
+}
+
+// the result of a probe that has to finish within PROBE_TIMEOUT, `target` names it in the result
+async fn timed<F>(target: String, probe: F) -> PingResult
+where
+    F: std::future::Future<Output = anyhow::Result<()>>,
+{
+    let start = Instant::now();
+    match tokio::time::timeout(PROBE_TIMEOUT, probe).await {
+        Ok(Ok(())) => PingResult::Pong(start.elapsed(), target),
+        Ok(Err(err)) => PingResult::Unknown(format!("{:#}", err)),
+        Err(_) => PingResult::Timeout(target),
+    }
+}
+
+// a socket bound to the wildcard address of the family of `addr`
+async fn udp_socket(addr: SocketAddr) -> anyhow::Result<UdpSocket> {
+    let local: SocketAddr = match addr {
+        SocketAddr::V4(_) => "0.0.0.0:0".parse()?,
+        SocketAddr::V6(_) => "[::]:0".parse()?,
+    };
+    let socket = UdpSocket::bind(local).await?;
+    socket.connect(addr).await?;
+    Ok(socket)
+}
+
+/// the time it takes to open a tcp connection to a port
+pub struct TcpProber {
+    addr: SocketAddr,
+}
+
+impl Prober for TcpProber {
+    fn probe(&mut self, _seq: u16) -> ProbeFuture<'_> {
+        let addr = self.addr;
+        Box::pin(timed(addr.to_string(), async move {
+            TcpStream::connect(addr).await.with_context(|| format!("connect to {}", addr))?;
+            Ok(())
+        }))
+    }
+}
+
+/// the time until a datagram sent to a port is answered, for services that echo or reply
+pub struct UdpProber {
+    addr: SocketAddr,
+}
+
+impl Prober for UdpProber {
+    fn probe(&mut self, seq: u16) -> ProbeFuture<'_> {
+        let addr = self.addr;
+        Box::pin(timed(addr.to_string(), async move {
+            // a socket per probe, a late reply must not count for the next one
+            let socket = udp_socket(addr).await?;
+            socket.send(format!("nping {}", seq).as_bytes()).await?;
+            let mut buf = [0; 512];
+            socket.recv(&mut buf).await.with_context(|| format!("no reply from {}", addr))?;
+            Ok(())
+        }))
+    }
+}
+
+/// the time until the status line of a plain http `GET /` arrives, any status counts as a reply
+pub struct HttpProber {
+    addr: SocketAddr,
+    /// sent as the host header
+    host: String,
+}
+
+impl Prober for HttpProber {
+    fn probe(&mut self, _seq: u16) -> ProbeFuture<'_> {
+        let addr = self.addr;
+        let host = match addr.port() {
+            80 => self.host.clone(),
+            port => format!("{}:{}", self.host, port),
+        };
+        Box::pin(timed(addr.to_string(), async move {
+            let mut stream = TcpStream::connect(addr).await.with_context(|| format!("connect to {}", addr))?;
+            let request = format!("GET / HTTP/1.1\r\nHost: {}\r\nUser-Agent: nping\r\nConnection: close\r\n\r\n", host);
+            stream.write_all(request.as_bytes()).await?;
+            let mut buf = [0; 16];
+            let read = stream.read(&mut buf).await?;
+            if !buf[..read].starts_with(b"HTTP/") {
+                return Err(anyhow!("{} did not answer with http", addr));
+            }
+            Ok(())
+        }))
+    }
+}
+
+/// the time until a dns server answers a query for the name servers of the root zone
+pub struct DnsProber {
+    addr: SocketAddr,
+}
+
+impl Prober for DnsProber {
+    fn probe(&mut self, seq: u16) -> ProbeFuture<'_> {
+        let addr = self.addr;
+        Box::pin(timed(addr.to_string(), async move {
+            let socket = udp_socket(addr).await?;
+            let id = seq.to_be_bytes();
+            // header: id, recursion desired, one question; question: the root name, type NS, class IN
+            let query = [id[0], id[1], 0x01, 0x00, 0, 1, 0, 0, 0, 0, 0, 0, 0, 0, 2, 0, 1];
+            socket.send(&query).await?;
+            let mut buf = [0; 512];
+            loop {
+                let read = socket.recv(&mut buf).await.with_context(|| format!("no answer from {}", addr))?;
+                // answers to earlier queries are skipped
+                if read >= 12 && buf[..2] == id {
+                    return Ok(());
+                }
+            }
+        }))
+    }
+}
+
+/// the latency and loss of a simulated target
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SimOptions {
+    /// average rtt
+    pub latency: Duration,
+    /// the rtt varies up to this much around the latency
+    pub jitter: Duration,
+    /// share of the probes lost, in percent
+    pub loss: f64,
+    /// seed of the random rtts and losses, the same seed gives the same probes
+    pub seed: Option<u64>,
+}
+
+impl Default for SimOptions {
+    fn default() -> Self {
+        Self {
+            latency: Duration::from_millis(20),
+            jitter: Duration::from_millis(5),
+            loss: 0.0,
+            seed: None,
+        }
+    }
+}
+
+impl FromStr for SimOptions {
+    type Err = String;
+
+    /// the settings after `sim://`: `lat=20ms,jitter=5ms,loss=2%,seed=1`, any of them may be left out
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let mut options = SimOptions::default();
+        for setting in value.split(',').filter(|setting| !setting.is_empty()) {
+            let (key, value) = setting
+                .split_once('=')
+                .ok_or_else(|| format!("expected key=value in {}{}, got: {}", SIM_SCHEME, value, setting))?;
+            match key {
+                "lat" | "latency" => options.latency = sim_duration(value)?,
+                "jitter" => options.jitter = sim_duration(value)?,
+                "loss" => options.loss = parse_loss(value)?,
+                "seed" => options.seed = Some(value.parse().map_err(|_| format!("invalid seed: {}", value))?),
+                _ => return Err(format!("unknown sim setting: {} (supported: lat, jitter, loss, seed)", key)),
+            }
+        }
+        Ok(options)
+    }
+}
+
+// a duration that may also be zero, e.g. no jitter
+fn sim_duration(value: &str) -> Result<Duration, String> {
+    if value.trim_end_matches(char::is_alphabetic).parse::<f64>() == Ok(0.0) {
+        return Ok(Duration::ZERO);
+    }
+    parse_duration(value)
+}
+
+/// synthetic replies with a random rtt around the latency, without network access
+pub struct SimProber {
+    target: String,
+    options: SimOptions,
+    rng: StdRng,
+}
+
+impl SimProber {
+    /// simulate a target with the options, a seed makes a run repeatable
+    pub fn new(target: impl ToString, options: SimOptions) -> Self {
+        Self {
+            target: target.to_string(),
+            options,
+            rng: match options.seed {
+                Some(seed) => StdRng::seed_from_u64(seed),
+                None => StdRng::from_os_rng(),
+            },
+        }
+    }
+}
+
+impl Prober for SimProber {
+    fn probe(&mut self, _seq: u16) -> ProbeFuture<'_> {
+        let SimOptions { latency, jitter, loss, .. } = self.options;
+        let offset = jitter.as_secs_f64() * self.rng.random_range(-1.0..=1.0);
+        let rtt = Duration::from_secs_f64((latency.as_secs_f64() + offset).max(0.0));
+        let lost = self.rng.random_range(0.0..100.0) < loss;
+        let target = self.target.clone();
+        Box::pin(async move {
+            // a lost probe does not wait for a timeout, the simulated target is only slow when told to
+            tokio::time::sleep(rtt).await;
+            if lost {
+                PingResult::Timeout(target)
+            } else {
+                PingResult::Pong(rtt, target)
+            }
+        })
+    }
+}
@@ -0,0 +1,243 @@
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, LineWriter, Write};
+use std::path::Path;
+use std::time::{Duration, Instant};
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use nping::ip_data::IpData;
+use nping::network::{IpFamily, PingEvent};
+use nping::target::TargetOptions;
+
+/// version of the recording format, bumped on incompatible changes
+const RECORD_VERSION: u32 = 1;
+
+/// one line of a recording, a json object with the milliseconds since the start and a type
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct RecordLine {
+    pub(crate) t: u64,
+    #[serde(flatten)]
+    pub(crate) entry: Entry,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub(crate) enum Entry {
+    /// the first line, when recording started
+    Start { version: u32, time: DateTime<Local> },
+    /// a row was added
+    Target {
+        id: usize,
+        host: String,
+        ip: String,
+        label: Option<String>,
+        group: Option<String>,
+        family: Option<IpFamily>,
+        options: TargetOptions,
+    },
+    /// a row was removed
+    Remove { id: usize },
+    Pong { id: usize, rtt: f64 },
+    Timeout { id: usize },
+    Resolved { id: usize, ip: String, addresses: Vec<String> },
+    Unresolved { id: usize },
+}
+
+impl Entry {
+    fn from_event(event: &PingEvent) -> Self {
+        match event.clone() {
+            PingEvent::Pong { id, rtt } => Entry::Pong { id, rtt },
+            PingEvent::Timeout { id } => Entry::Timeout { id },
+            PingEvent::Resolved { id, ip, addresses } => Entry::Resolved { id, ip, addresses },
+            PingEvent::Unresolved { id } => Entry::Unresolved { id },
+        }
+    }
+
+    /// the probe result of a recorded event, None for the other entries
+    pub(crate) fn to_event(&self) -> Option<PingEvent> {
+        match self.clone() {
+            Entry::Pong { id, rtt } => Some(PingEvent::Pong { id, rtt }),
+            Entry::Timeout { id } => Some(PingEvent::Timeout { id }),
+            Entry::Resolved { id, ip, addresses } => Some(PingEvent::Resolved { id, ip, addresses }),
+            Entry::Unresolved { id } => Some(PingEvent::Unresolved { id }),
+            Entry::Start { .. } | Entry::Target { .. } | Entry::Remove { .. } => None,
+        }
+    }
+}
+
+/// writes the probe stream of a session to a file, one json line per entry
+#[derive(Debug)]
+pub(crate) struct Recorder {
+    file: LineWriter<File>,
+    started: Instant,
+    /// ids of the rows already written
+    targets: HashSet<usize>,
+}
+
+impl Recorder {
+    pub(crate) fn create(path: &Path) -> Result<Self, String> {
+        let file = File::create(path).map_err(|err| format!("failed to create recording {}: {}", path.display(), err))?;
+        let mut recorder = Self {
+            file: LineWriter::new(file),
+            started: Instant::now(),
+            targets: HashSet::new(),
+        };
+        recorder
+            .write(Entry::Start { version: RECORD_VERSION, time: Local::now() })
+            .map_err(|err| format!("failed to write recording {}: {}", path.display(), err))?;
+        Ok(recorder)
+    }
+
+    /// record the rows added and removed since the last call
+    pub(crate) fn sync_targets(&mut self, ip_data: &[IpData]) -> io::Result<()> {
+        let removed: Vec<usize> = self.targets.iter().copied().filter(|id| !ip_data.iter().any(|data| data.id == *id)).collect();
+        for id in removed {
+            self.targets.remove(&id);
+            self.write(Entry::Remove { id })?;
+        }
+        for data in ip_data {
+            if !self.targets.insert(data.id) {
+                continue;
+            }
+            self.write(Entry::Target {
+                id: data.id,
+                host: data.addr.clone(),
+                ip: data.ip.clone(),
+                label: data.label.clone(),
+                group: data.group.clone(),
+                family: data.family,
+                options: data.options.clone(),
+            })?;
+        }
+        Ok(())
+    }
+
+    pub(crate) fn event(&mut self, event: &PingEvent) -> io::Result<()> {
+        self.write(Entry::from_event(event))
+    }
+
+    fn write(&mut self, entry: Entry) -> io::Result<()> {
+        let line = RecordLine { t: self.started.elapsed().as_millis() as u64, entry };
+        serde_json::to_writer(&mut self.file, &line)?;
+        self.file.write_all(b"\n")
+    }
+}
+
+/// a recorded session, read back for replaying
+#[derive(Debug, Clone)]
+pub(crate) struct Recording {
+    /// when recording started
+    pub(crate) started: DateTime<Local>,
+    /// the entries after the start line, in the order they were recorded
+    pub(crate) entries: Vec<(Duration, Entry)>,
+}
+
+impl Recording {
+    pub(crate) fn read(path: &Path) -> Result<Self, String> {
+        let file = File::open(path).map_err(|err| format!("failed to open recording {}: {}", path.display(), err))?;
+        let mut started = None;
+        let mut entries = Vec::new();
+        let mut lines = BufReader::new(file).lines().enumerate().peekable();
+        while let Some((number, line)) = lines.next() {
+            let line = line.map_err(|err| format!("{}: {}", path.display(), err))?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let record: RecordLine = match serde_json::from_str(&line) {
+                Ok(record) => record,
+                // the last line is cut short when nping was killed while recording
+                Err(err) if err.is_eof() && lines.peek().is_none() => break,
+                Err(err) => return Err(format!("{}: line {}: {}", path.display(), number + 1, err)),
+            };
+            match record.entry {
+                Entry::Start { version, time } if number == 0 => {
+                    if version > RECORD_VERSION {
+                        return Err(format!("{}: recording version {} is not supported", path.display(), version));
+                    }
+                    started = Some(time);
+                }
+                _ if started.is_none() => return Err(format!("{} is not an nping recording", path.display())),
+                entry => entries.push((Duration::from_millis(record.t), entry)),
+            }
+        }
+        let started = started.ok_or_else(|| format!("{} is empty", path.display()))?;
+        Ok(Self { started, entries })
+    }
+
+    /// the time of the last entry
+    pub(crate) fn duration(&self) -> Duration {
+        self.entries.last().map(|(t, _)| *t).unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn recording_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("nping-{}-{}", std::process::id(), name))
+    }
+
+    // read a recording with the given lines
+    fn read(name: &str, lines: &[&str]) -> Result<Recording, String> {
+        let path = recording_path(name);
+        std::fs::write(&path, lines.join("\n")).unwrap();
+        let recording = Recording::read(&path);
+        std::fs::remove_file(path).unwrap();
+        recording
+    }
+
+    const START: &str = r#"{"t":0,"type":"start","version":1,"time":"2026-01-01T10:00:00+00:00"}"#;
+
+    #[test]
+    fn reads_back_what_was_recorded() {
+        let path = recording_path("round-trip.jsonl");
+        let mut first = IpData::new(0, "example.com".to_string(), "10.0.0.1".to_string());
+        first.label = Some("web".to_string());
+        first.family = Some(IpFamily::V4);
+        let second = IpData::new(1, "10.0.0.2".to_string(), "10.0.0.2".to_string());
+
+        let mut recorder = Recorder::create(&path).unwrap();
+        recorder.sync_targets(&[first.clone(), second.clone()]).unwrap();
+        recorder.event(&PingEvent::Pong { id: 0, rtt: 12.5 }).unwrap();
+        recorder.event(&PingEvent::Timeout { id: 1 }).unwrap();
+        recorder.event(&PingEvent::Resolved { id: 0, ip: "10.0.0.9".to_string(), addresses: vec!["10.0.0.9".to_string()] }).unwrap();
+        // the second target was removed, the first one is not written again
+        recorder.sync_targets(&[first]).unwrap();
+        drop(recorder);
+
+        let recording = Recording::read(&path).unwrap();
+        std::fs::remove_file(path).unwrap();
+        assert!((Local::now() - recording.started).num_seconds() < 60);
+        let entries: Vec<&Entry> = recording.entries.iter().map(|(_, entry)| entry).collect();
+        assert_eq!(entries.len(), 6, "{:?}", entries);
+        assert!(matches!(entries[0], Entry::Target { id: 0, host, label: Some(label), family: Some(IpFamily::V4), .. } if host == "example.com" && label == "web"));
+        assert!(matches!(entries[1], Entry::Target { id: 1, ip, label: None, .. } if ip == "10.0.0.2"));
+        assert!(matches!(entries[2].to_event(), Some(PingEvent::Pong { id: 0, rtt }) if rtt == 12.5));
+        assert!(matches!(entries[3].to_event(), Some(PingEvent::Timeout { id: 1 })));
+        assert!(matches!(entries[4].to_event(), Some(PingEvent::Resolved { id: 0, ip, .. }) if ip == "10.0.0.9"));
+        assert!(matches!(entries[5], Entry::Remove { id: 1 }));
+    }
+
+    #[test]
+    fn ignores_a_truncated_last_line() {
+        let recording = read("truncated.jsonl", &[START, r#"{"t":1000,"type":"pong","id":0,"rtt":5.0}"#, r#"{"t":2000,"type":"po"#]).unwrap();
+        assert_eq!(recording.entries.len(), 1);
+        assert_eq!(recording.duration(), Duration::from_secs(1));
+
+        // a broken line in the middle is an error
+        let err = read("broken.jsonl", &[START, r#"{"t":1000,"type":"po"#, r#"{"t":2000,"type":"timeout","id":0}"#]).unwrap_err();
+        assert!(err.contains("line 2"), "{}", err);
+    }
+
+    #[test]
+    fn rejects_other_files() {
+        let err = read("version.jsonl", &[r#"{"t":0,"type":"start","version":2,"time":"2026-01-01T10:00:00+00:00"}"#]).unwrap_err();
+        assert!(err.ends_with("recording version 2 is not supported"), "{}", err);
+        let err = read("no-start.jsonl", &[r#"{"t":0,"type":"timeout","id":0}"#]).unwrap_err();
+        assert!(err.ends_with("is not an nping recording"), "{}", err);
+        let err = read("empty.jsonl", &[]).unwrap_err();
+        assert!(err.ends_with("is empty"), "{}", err);
+    }
+}
@@ -0,0 +1,277 @@
+use std::error::Error;
+use std::path::Path;
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use chrono::{DateTime, Local};
+use ratatui::crossterm::event::{KeyCode, KeyEvent};
+use crate::app::{AppState, KeyAction};
+use crate::draw;
+use nping::error_log::ErrorLog;
+use nping::health::Thresholds;
+use nping::ip_data::{apply_event, IpData};
+use crate::record::{Entry, Recording};
+use nping::status::EventLog;
+use crate::terminal::TerminalGuard;
+
+/// how far the arrow keys seek, page up and down seek a minute
+const SEEK_STEP: Duration = Duration::from_secs(10);
+const SEEK_PAGE: Duration = Duration::from_secs(60);
+
+/// the playback speeds + and - step through
+const SPEEDS: [f64; 9] = [0.25, 0.5, 1.0, 2.0, 4.0, 8.0, 16.0, 32.0, 64.0];
+
+/// where the replay of a recording is, shown at the bottom of the screen
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct ReplayProgress {
+    /// when the recording was started
+    pub(crate) started: DateTime<Local>,
+    /// time into the recording
+    pub(crate) position: Duration,
+    /// length of the recording
+    pub(crate) duration: Duration,
+    pub(crate) speed: f64,
+    pub(crate) playing: bool,
+}
+
+impl ReplayProgress {
+    /// the recorded time at the current position
+    pub(crate) fn time(&self) -> DateTime<Local> {
+        self.started + chrono::Duration::from_std(self.position).unwrap_or_default()
+    }
+
+    fn seek(&mut self, position: Duration) {
+        self.position = position.min(self.duration);
+    }
+
+    fn change_speed(&mut self, step: isize) {
+        let current = SPEEDS.iter().position(|&speed| speed >= self.speed).unwrap_or(SPEEDS.len() - 1);
+        let next = (current as isize + step).clamp(0, SPEEDS.len() as isize - 1);
+        self.speed = SPEEDS[next as usize];
+    }
+}
+
+/// the targets and their statistics at some point of a recording
+#[derive(Debug, Default)]
+pub(crate) struct Player {
+    pub(crate) ip_data: Vec<IpData>,
+    pub(crate) events: EventLog,
+    /// index of the next entry to apply
+    next: usize,
+    /// recording time of the last applied entry
+    applied: Duration,
+}
+
+impl Player {
+    /// apply the entries up to `position`, starting over when seeking backwards.
+    /// returns the indices of the removed targets, in the order they were removed
+    pub(crate) fn advance(&mut self, recording: &Recording, position: Duration, point_num: usize, thresholds: &Thresholds) -> Vec<usize> {
+        let mut removed = Vec::new();
+        if position < self.applied {
+            *self = Player::default();
+        }
+        while let Some((t, entry)) = recording.entries.get(self.next) {
+            if *t > position {
+                break;
+            }
+            let time = recording.started + chrono::Duration::from_std(*t).unwrap_or_default();
+            match entry {
+                Entry::Target { id, host, ip, label, group, family, options } => {
+                    let mut data = IpData::new(*id, host.clone(), ip.clone());
+                    data.label = label.clone();
+                    data.group = group.clone();
+                    data.family = *family;
+                    data.options = options.clone();
+                    self.ip_data.push(data);
+                }
+                Entry::Remove { id } => {
+                    if let Some(index) = self.ip_data.iter().position(|data| data.id == *id) {
+                        self.ip_data.remove(index);
+                        removed.push(index);
+                    }
+                }
+                entry => {
+                    if let Some(event) = entry.to_event() {
+                        apply_event(&mut self.ip_data, event, point_num, thresholds, &mut self.events, time);
+                    }
+                }
+            }
+            self.applied = *t;
+            self.next += 1;
+        }
+        removed
+    }
+}
+
+/// show a recording in the tui, with play/pause, seeking and speed control
+pub(crate) fn run_replay(
+    path: &Path,
+    view_type: String,
+    thresholds: Thresholds,
+    key_rx: mpsc::Receiver<KeyEvent>,
+) -> Result<(), Box<dyn Error>> {
+    let recording = Recording::read(path)?;
+    let point_num = draw::point_num(&view_type);
+
+    // the paused flag only drives the paused marker, there is nothing probing
+    let paused = Arc::new(Mutex::new(false));
+    let mut state = AppState::new(view_type, paused.clone(), thresholds);
+    let mut progress = ReplayProgress {
+        started: recording.started,
+        position: Duration::ZERO,
+        duration: recording.duration(),
+        speed: 1.0,
+        playing: true,
+    };
+    let mut player = Player::default();
+    let errs = ErrorLog::default();
+
+    let mut guard = TerminalGuard::new(draw::init_terminal()?);
+    let mut last_tick = Instant::now();
+    loop {
+        let mut keys = Vec::new();
+        match key_rx.recv_timeout(Duration::from_millis(50)) {
+            Ok(key) => keys.push(key),
+            Err(mpsc::RecvTimeoutError::Timeout) => {}
+            Err(mpsc::RecvTimeoutError::Disconnected) => break,
+        }
+        keys.extend(key_rx.try_iter());
+
+        let mut quit = false;
+        for key in keys {
+            if state.input.is_some() {
+                state.handle_key(key, &mut player.ip_data);
+                continue;
+            }
+            match key.code {
+                KeyCode::Char(' ') | KeyCode::Char('p') => {
+                    // playing again at the end starts over
+                    if !progress.playing && progress.position >= progress.duration {
+                        progress.seek(Duration::ZERO);
+                    }
+                    progress.playing = !progress.playing;
+                }
+                KeyCode::Left => progress.seek(progress.position.saturating_sub(SEEK_STEP)),
+                KeyCode::Right => progress.seek(progress.position + SEEK_STEP),
+                KeyCode::PageUp => progress.seek(progress.position.saturating_sub(SEEK_PAGE)),
+                KeyCode::PageDown => progress.seek(progress.position + SEEK_PAGE),
+                KeyCode::Home => progress.seek(Duration::ZERO),
+                KeyCode::End => progress.seek(progress.duration),
+                KeyCode::Char('+') | KeyCode::Char('=') => progress.change_speed(1),
+                KeyCode::Char('-') => progress.change_speed(-1),
+                // the recorded targets cannot be changed or reset
+                KeyCode::Char('a') | KeyCode::Char('d') | KeyCode::Char('r') | KeyCode::Char('R') => {}
+                _ => {
+                    if state.handle_key(key, &mut player.ip_data) == KeyAction::Quit {
+                        quit = true;
+                    }
+                }
+            }
+        }
+        if quit {
+            break;
+        }
+
+        if progress.playing {
+            progress.seek(progress.position + last_tick.elapsed().mul_f64(progress.speed));
+            progress.playing = progress.position < progress.duration;
+        }
+        last_tick = Instant::now();
+        for index in player.advance(&recording, progress.position, point_num, &state.thresholds) {
+            state.target_removed(index, player.ip_data.len());
+        }
+
+        *paused.lock().unwrap() = !progress.playing;
+        state.replay = Some(progress);
+        draw::draw_interface(guard.terminal.as_mut().unwrap(), &player.ip_data, &errs, &player.events, &state).ok();
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nping::target::TargetOptions;
+
+    fn target(id: usize, host: &str) -> Entry {
+        Entry::Target {
+            id,
+            host: host.to_string(),
+            ip: host.to_string(),
+            label: None,
+            group: None,
+            family: None,
+            options: TargetOptions::default(),
+        }
+    }
+
+    // three targets pinged once a second, the second one is removed after 3s
+    fn recording() -> Recording {
+        let mut entries = vec![
+            (Duration::ZERO, target(0, "10.0.0.1")),
+            (Duration::ZERO, target(1, "10.0.0.2")),
+            (Duration::ZERO, target(2, "10.0.0.3")),
+        ];
+        for secs in 1..=5 {
+            for id in 0..3 {
+                if id != 1 || secs <= 3 {
+                    entries.push((Duration::from_secs(secs), Entry::Pong { id, rtt: secs as f64 }));
+                }
+            }
+        }
+        entries.push((Duration::from_millis(3500), Entry::Remove { id: 1 }));
+        entries.sort_by_key(|(t, _)| *t);
+        Recording { started: Local::now(), entries }
+    }
+
+    fn rtts(player: &Player) -> Vec<Vec<f64>> {
+        player.ip_data.iter().map(|data| data.rtts.iter().copied().collect()).collect()
+    }
+
+    #[test]
+    fn advances_to_a_position() {
+        let recording = recording();
+        let mut player = Player::default();
+        assert!(player.advance(&recording, Duration::from_secs(2), 10, &Thresholds::default()).is_empty());
+        assert_eq!(rtts(&player), [vec![1.0, 2.0], vec![1.0, 2.0], vec![1.0, 2.0]]);
+
+        // only the new entries are applied, point_num limits the rtts kept
+        player.advance(&recording, Duration::from_secs(3), 2, &Thresholds::default());
+        assert_eq!(rtts(&player), [vec![2.0, 3.0], vec![2.0, 3.0], vec![2.0, 3.0]]);
+        assert_eq!(player.ip_data[0].received, 3);
+    }
+
+    #[test]
+    fn tells_the_removed_targets() {
+        let recording = recording();
+        let mut player = Player::default();
+        player.advance(&recording, Duration::from_secs(3), 10, &Thresholds::default());
+        assert_eq!(player.advance(&recording, Duration::from_secs(4), 10, &Thresholds::default()), [1]);
+        let hosts: Vec<&str> = player.ip_data.iter().map(|data| data.addr.as_str()).collect();
+        assert_eq!(hosts, ["10.0.0.1", "10.0.0.3"]);
+        assert_eq!(rtts(&player)[1], [1.0, 2.0, 3.0, 4.0]);
+    }
+
+    #[test]
+    fn seeking_backwards_starts_over() {
+        let recording = recording();
+        let mut player = Player::default();
+        player.advance(&recording, recording.duration(), 10, &Thresholds::default());
+        assert_eq!(player.ip_data.len(), 2);
+
+        // the removed target is back and nothing after the position is kept
+        assert!(player.advance(&recording, Duration::from_secs(1), 10, &Thresholds::default()).is_empty());
+        assert_eq!(rtts(&player), [vec![1.0], vec![1.0], vec![1.0]]);
+        assert_eq!(player.ip_data[0].received, 1);
+
+        assert!(player.advance(&recording, Duration::ZERO, 10, &Thresholds::default()).is_empty());
+        assert_eq!(rtts(&player), [Vec::<f64>::new(), vec![], vec![]]);
+    }
+
+    #[test]
+    fn keeps_more_points_for_the_point_view() {
+        assert_eq!(draw::point_num("point"), 200);
+        assert_eq!(draw::point_num("graph"), 10);
+        assert_eq!(draw::point_num("table"), 10);
+    }
+}
@@ -0,0 +1,320 @@
+use std::fmt::Write as _;
+use std::io::{self, Write};
+use std::path::Path;
+use nping::health::Thresholds;
+use nping::ip_data::IpData;
+use nping::network::IpFamily;
+use crate::record::Recording;
+use crate::replay::Player;
+use nping::status::format_duration;
+use nping::stats::{calculate_avg_rtt, calculate_jitter, calculate_loss_pkg, calculate_percentile};
+
+/// print the statistics, outages and state changes of every target of a recording
+pub(crate) fn print_report(path: &Path, thresholds: &Thresholds) -> Result<(), String> {
+    print(&report(path, thresholds)?)
+}
+
+/// print the statistics of the targets of two recordings side by side
+pub(crate) fn print_compare(before: &Path, after: &Path, thresholds: &Thresholds) -> Result<(), String> {
+    print(&compare(before, after, thresholds)?)
+}
+
+fn report(path: &Path, thresholds: &Thresholds) -> Result<String, String> {
+    let recording = Recording::read(path)?;
+    let player = replay_all(&recording, thresholds);
+    let end = recording.started + chrono::Duration::from_std(recording.duration()).unwrap_or_default();
+    let mut out = String::new();
+
+    let _ = writeln!(
+        out,
+        "{}: {} - {} ({})",
+        path.display(),
+        recording.started.format("%Y-%m-%d %H:%M:%S"),
+        end.format("%Y-%m-%d %H:%M:%S"),
+        format_duration(recording.duration())
+    );
+    let _ = writeln!(out);
+
+    let header = ["Target", "Ip", "Sent", "Loss", "Min", "Avg", "Max", "Jitter", "P95", "Status", "Outages", "Downtime"];
+    let rows = player
+        .ip_data
+        .iter()
+        .map(|data| {
+            vec![
+                data.name().to_string(),
+                data.ip.clone(),
+                (data.received + data.timeout).to_string(),
+                format!("{:.2}%", calculate_loss_pkg(data.timeout, data.received)),
+                format!("{:.2}ms", data.min_rtt),
+                format!("{:.2}ms", calculate_avg_rtt(&data.rtts)),
+                format!("{:.2}ms", data.max_rtt),
+                format!("{:.2}ms", calculate_jitter(&data.rtts)),
+                format!("{:.2}ms", calculate_percentile(&data.rtts, 95.0)),
+                data.status.status.to_string(),
+                data.status.outages.to_string(),
+                format_duration(data.status.downtime(end)),
+            ]
+        })
+        .collect();
+    print_table(&mut out, &header, rows);
+
+    let _ = writeln!(out);
+    let events: Vec<_> = player.events.recent().collect();
+    if events.is_empty() {
+        let _ = writeln!(out, "No state changes");
+    }
+    for event in events.into_iter().rev() {
+        let _ = writeln!(
+            out,
+            "{}  {}: {} -> {}  after {}{}",
+            event.time.format("%Y-%m-%d %H:%M:%S"),
+            event.target,
+            event.from,
+            event.to,
+            format_duration(event.duration),
+            if event.flapping { "  flapping" } else { "" }
+        );
+    }
+    Ok(out)
+}
+
+fn compare(before: &Path, after: &Path, thresholds: &Thresholds) -> Result<String, String> {
+    let before_data = replay_all(&Recording::read(before)?, thresholds).ip_data;
+    let after_data = replay_all(&Recording::read(after)?, thresholds).ip_data;
+
+    let mut out = String::new();
+    let _ = writeln!(out, "{} -> {}", before.display(), after.display());
+    let _ = writeln!(out);
+
+    // targets are matched by host and address family, the address may have changed in between, and
+    // the rows of a host pinged on several addresses are taken together
+    let key = |data: &IpData| (data.addr.clone(), data.family);
+    let mut keys: Vec<(String, Option<IpFamily>)> = Vec::new();
+    for data in before_data.iter().chain(&after_data) {
+        if !keys.contains(&key(data)) {
+            keys.push(key(data));
+        }
+    }
+
+    let header = ["Target", "Avg before", "Avg after", "Avg Δ", "Loss before", "Loss after", "Loss Δ", "Outages"];
+    let rows = keys
+        .iter()
+        .map(|target| {
+            let before: Vec<&IpData> = before_data.iter().filter(|data| key(data) == *target).collect();
+            let after: Vec<&IpData> = after_data.iter().filter(|data| key(data) == *target).collect();
+            let name = after.first().or(before.first()).map(|data| data.name().to_string()).unwrap_or_default();
+            let addresses = before.len().max(after.len());
+            let name = match (target.1, addresses) {
+                (Some(family), 1) => format!("{} (ip{})", name, family),
+                (Some(family), _) => format!("{} (ip{}, {} addresses)", name, family, addresses),
+                (None, 1) => name,
+                (None, _) => format!("{} ({} addresses)", name, addresses),
+            };
+            vec![
+                name,
+                value(avg(&before), "ms"),
+                value(avg(&after), "ms"),
+                change(avg(&before), avg(&after), "ms"),
+                value(loss(&before), "%"),
+                value(loss(&after), "%"),
+                change(loss(&before), loss(&after), "%"),
+                format!("{} -> {}", outages(&before), outages(&after)),
+            ]
+        })
+        .collect();
+    print_table(&mut out, &header, rows);
+    Ok(out)
+}
+
+// every target of a recording with the statistics over the whole recording
+fn replay_all(recording: &Recording, thresholds: &Thresholds) -> Player {
+    let mut player = Player::default();
+    // keep every rtt so the averages cover the whole recording
+    player.advance(recording, recording.duration(), usize::MAX, thresholds);
+    player
+}
+
+// the average rtt of the replies of all rows, None without rows
+fn avg(rows: &[&IpData]) -> Option<f64> {
+    let received: usize = rows.iter().map(|data| data.received).sum();
+    let total: f64 = rows.iter().map(|data| data.session_avg_rtt() * data.received as f64).sum();
+    (!rows.is_empty()).then(|| if received > 0 { total / received as f64 } else { 0.0 })
+}
+
+fn loss(rows: &[&IpData]) -> Option<f64> {
+    let timeout = rows.iter().map(|data| data.timeout).sum();
+    let received = rows.iter().map(|data| data.received).sum();
+    (!rows.is_empty()).then(|| calculate_loss_pkg(timeout, received))
+}
+
+fn outages(rows: &[&IpData]) -> String {
+    match rows {
+        [] => "-".to_string(),
+        rows => rows.iter().map(|data| data.status.outages).sum::<usize>().to_string(),
+    }
+}
+
+fn value(value: Option<f64>, unit: &str) -> String {
+    value.map_or("-".to_string(), |value| format!("{:.2}{}", value, unit))
+}
+
+fn change(before: Option<f64>, after: Option<f64>, unit: &str) -> String {
+    match (before, after) {
+        (Some(before), Some(after)) => format!("{:+.2}{}", after - before, unit),
+        _ => "-".to_string(),
+    }
+}
+
+// write to stdout, a closed pipe like `nping report x | head` is not an error
+fn print(out: &str) -> Result<(), String> {
+    match io::stdout().write_all(out.as_bytes()) {
+        Err(err) if err.kind() != io::ErrorKind::BrokenPipe => Err(err.to_string()),
+        _ => Ok(()),
+    }
+}
+
+// left aligned columns, two spaces apart
+fn print_table(out: &mut String, header: &[&str], rows: Vec<Vec<String>>) {
+    let widths: Vec<usize> = (0..header.len())
+        .map(|column| {
+            rows.iter()
+                .map(|row| row[column].chars().count())
+                .chain([header[column].chars().count()])
+                .max()
+                .unwrap_or(0)
+        })
+        .collect();
+    let mut line = |cells: Vec<String>| {
+        let cells: Vec<String> = cells
+            .iter()
+            .zip(&widths)
+            .map(|(cell, width)| format!("{:<width$}", cell, width = width))
+            .collect();
+        let _ = writeln!(out, "{}", cells.join("  ").trim_end());
+    };
+    line(header.iter().map(|cell| cell.to_string()).collect());
+    for row in rows {
+        line(row);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    // a recording of a target that goes down for three probes and one that answers every probe
+    const SESSION: &str = r#"{"t":0,"type":"start","version":1,"time":"2026-01-01T10:00:00+00:00"}
+{"t":0,"type":"target","id":0,"host":"a.example","ip":"10.0.0.1","label":null,"group":null,"family":null,"options":{}}
+{"t":0,"type":"target","id":1,"host":"b.example","ip":"10.0.0.2","label":null,"group":null,"family":null,"options":{}}
+{"t":1000,"type":"pong","id":0,"rtt":10.0}
+{"t":1000,"type":"pong","id":1,"rtt":5.0}
+{"t":2000,"type":"pong","id":0,"rtt":20.0}
+{"t":2000,"type":"pong","id":1,"rtt":5.0}
+{"t":3000,"type":"timeout","id":0}
+{"t":4000,"type":"timeout","id":0}
+{"t":5000,"type":"timeout","id":0}
+{"t":6000,"type":"pong","id":0,"rtt":10.0}
+{"t":7000,"type":"pong","id":0,"rtt":20.0}
+"#;
+
+    fn recording(name: &str, content: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("nping-{}-{}", std::process::id(), name));
+        std::fs::write(&path, content).unwrap();
+        path
+    }
+
+    fn row<'a>(out: &'a str, target: &str) -> Vec<&'a str> {
+        let line = out.lines().find(|line| line.starts_with(target)).unwrap();
+        line.split("  ").map(str::trim).filter(|cell| !cell.is_empty()).collect()
+    }
+
+    #[test]
+    fn reports_the_whole_session() {
+        let path = recording("report.jsonl", SESSION);
+        let out = report(&path, &Thresholds::default()).unwrap();
+        std::fs::remove_file(path).unwrap();
+
+        assert!(out.contains("(7s)"), "{}", out);
+        assert_eq!(
+            row(&out, "Target"),
+            ["Target", "Ip", "Sent", "Loss", "Min", "Avg", "Max", "Jitter", "P95", "Status", "Outages", "Downtime"]
+        );
+        assert_eq!(row(&out, "a.example")[..7], ["a.example", "10.0.0.1", "7", "42.86%", "10.00ms", "15.00ms", "20.00ms"]);
+        // any loss is a warning by default
+        assert_eq!(row(&out, "a.example")[9..], ["degraded", "1", "2s"]);
+        assert_eq!(row(&out, "b.example")[..6], ["b.example", "10.0.0.2", "2", "0.00%", "5.00ms", "5.00ms"]);
+        assert!(out.lines().any(|line| line.contains("a.example: degraded -> down  after 2s")), "{}", out);
+        assert!(out.lines().any(|line| line.contains("a.example: down -> degraded")), "{}", out);
+    }
+
+    #[test]
+    fn reports_a_session_without_changes() {
+        let path = recording("quiet.jsonl", r#"{"t":0,"type":"start","version":1,"time":"2026-01-01T10:00:00+00:00"}"#);
+        let out = report(&path, &Thresholds::default()).unwrap();
+        std::fs::remove_file(path).unwrap();
+        assert!(out.ends_with("No state changes\n"), "{}", out);
+    }
+
+    #[test]
+    fn compares_targets_by_host() {
+        let before = recording("before.jsonl", SESSION);
+        // a.example moved to another address and is faster, b.example is gone, c.example is new
+        let after = recording(
+            "after.jsonl",
+            r#"{"t":0,"type":"start","version":1,"time":"2026-01-02T10:00:00+00:00"}
+{"t":0,"type":"target","id":0,"host":"a.example","ip":"10.0.0.9","label":null,"group":null,"family":null,"options":{}}
+{"t":0,"type":"target","id":1,"host":"c.example","ip":"10.0.0.3","label":null,"group":null,"family":null,"options":{}}
+{"t":1000,"type":"pong","id":0,"rtt":5.0}
+{"t":1000,"type":"pong","id":1,"rtt":1.0}
+"#,
+        );
+        let out = compare(&before, &after, &Thresholds::default()).unwrap();
+        std::fs::remove_file(before).unwrap();
+        std::fs::remove_file(after).unwrap();
+
+        assert_eq!(row(&out, "a.example"), ["a.example", "15.00ms", "5.00ms", "-10.00ms", "42.86%", "0.00%", "-42.86%", "1 -> 0"]);
+        assert_eq!(row(&out, "b.example"), ["b.example", "5.00ms", "-", "-", "0.00%", "-", "-", "0 -> -"]);
+        assert_eq!(row(&out, "c.example"), ["c.example", "-", "1.00ms", "-", "-", "0.00%", "-", "- -> 0"]);
+    }
+
+    #[test]
+    fn compares_every_address_of_a_host() {
+        // a.example is pinged on two addresses with -m 2, both rows count
+        let before = recording(
+            "before-m.jsonl",
+            r#"{"t":0,"type":"start","version":1,"time":"2026-01-01T10:00:00+00:00"}
+{"t":0,"type":"target","id":0,"host":"a.example","ip":"10.0.0.1","label":null,"group":null,"family":null,"options":{}}
+{"t":0,"type":"target","id":1,"host":"a.example","ip":"10.0.0.2","label":null,"group":null,"family":null,"options":{}}
+{"t":1000,"type":"pong","id":0,"rtt":10.0}
+{"t":1000,"type":"pong","id":1,"rtt":30.0}
+{"t":2000,"type":"pong","id":0,"rtt":10.0}
+{"t":2000,"type":"timeout","id":1}
+"#,
+        );
+        let after = recording(
+            "after-m.jsonl",
+            r#"{"t":0,"type":"start","version":1,"time":"2026-01-02T10:00:00+00:00"}
+{"t":0,"type":"target","id":0,"host":"a.example","ip":"10.0.0.1","label":null,"group":null,"family":null,"options":{}}
+{"t":0,"type":"target","id":1,"host":"a.example","ip":"10.0.0.2","label":null,"group":null,"family":null,"options":{}}
+{"t":1000,"type":"pong","id":0,"rtt":5.0}
+{"t":1000,"type":"pong","id":1,"rtt":5.0}
+"#,
+        );
+        let out = compare(&before, &after, &Thresholds::default()).unwrap();
+        std::fs::remove_file(before).unwrap();
+        std::fs::remove_file(after).unwrap();
+
+        assert_eq!(out.lines().filter(|line| line.starts_with("a.example")).count(), 1, "{}", out);
+        assert_eq!(
+            row(&out, "a.example"),
+            ["a.example (2 addresses)", "16.67ms", "5.00ms", "-11.67ms", "25.00%", "0.00%", "-25.00%", "0 -> 0"]
+        );
+    }
+
+    #[test]
+    fn fails_on_a_missing_recording() {
+        let missing = std::env::temp_dir().join("nping-missing-recording.jsonl");
+        assert!(report(&missing, &Thresholds::default()).unwrap_err().contains("failed to open recording"));
+    }
+}
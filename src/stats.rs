@@ -0,0 +1,47 @@
+use std::collections::VecDeque;
+
+/// average of the valid rtts, timeouts are stored as -1.0 and skipped
+pub fn calculate_avg_rtt(rtt: &VecDeque<f64>) -> f64 {
+    if !rtt.is_empty() {
+        let valid_rtt: Vec<f64> = rtt.iter().cloned().filter(|&rtt| rtt >= 0.0).collect();
+        if !valid_rtt.is_empty() {
+            let sum: f64 = valid_rtt.iter().sum();
+            sum / valid_rtt.len() as f64
+        } else {
+            0.0
+        }
+    } else {
+        0.0
+    }
+}
+
+/// mean difference between consecutive rtts
+pub fn calculate_jitter(rtt: &VecDeque<f64>) -> f64 {
+    if rtt.len() > 1 {
+        let diffs: Vec<f64> = rtt.iter().zip(rtt.iter().skip(1)).map(|(y1, y2)| (y2 - y1).abs()).collect();
+        let sum: f64 = diffs.iter().sum();
+        sum / diffs.len() as f64
+    } else {
+        0.0
+    }
+}
+
+/// share of the probes that timed out, in percent
+pub fn calculate_loss_pkg(timeout: usize, received: usize) -> f64 {
+    if timeout > 0 {
+        (timeout as f64 / (received as f64 + timeout as f64)) * 100.0
+    } else {
+        0.0
+    }
+}
+
+/// nearest-rank percentile of the valid (non-timeout) rtts
+pub fn calculate_percentile(rtt: &VecDeque<f64>, percentile: f64) -> f64 {
+    let mut valid_rtt: Vec<f64> = rtt.iter().cloned().filter(|&rtt| rtt >= 0.0).collect();
+    if valid_rtt.is_empty() {
+        return 0.0;
+    }
+    valid_rtt.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+    let rank = ((percentile / 100.0) * valid_rtt.len() as f64).ceil() as usize;
+    valid_rtt[rank.clamp(1, valid_rtt.len()) - 1]
+}
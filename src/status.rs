@@ -0,0 +1,250 @@
+use std::collections::VecDeque;
+use std::fmt;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+use std::time::Duration;
+use chrono::{DateTime, Local};
+use crate::health::{Health, Thresholds};
+use crate::ip_data::IpData;
+
+/// a target is down after this many timeouts in a row, unless --down-after says otherwise
+pub const DOWN_AFTER: usize = 3;
+
+/// a down target is up again after this many replies in a row, unless --up-after says otherwise
+pub const UP_AFTER: usize = 2;
+
+/// a target is flapping when its state changed this often within FLAP_WINDOW
+const FLAP_CHANGES: usize = 4;
+const FLAP_WINDOW: Duration = Duration::from_secs(600);
+
+/// maximum number of events kept, older ones are dropped
+pub const EVENT_LOG_SIZE: usize = 500;
+
+/// the state of a target, changing with hysteresis so a single lost probe does not take it down
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Status {
+    /// no reply yet
+    #[default]
+    Unknown,
+    /// replying within the thresholds
+    Up,
+    /// replying, but above a loss or latency threshold
+    Degraded,
+    /// not replying
+    Down,
+}
+
+impl fmt::Display for Status {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Status::Unknown => "unknown",
+            Status::Up => "up",
+            Status::Degraded => "degraded",
+            Status::Down => "down",
+        };
+        f.pad(name)
+    }
+}
+
+/// follows the state of a single target probe by probe
+#[derive(Debug, Clone, Default)]
+pub struct StatusTracker {
+    /// the current state
+    pub status: Status,
+    /// when the target entered its current state
+    pub since: Option<DateTime<Local>>,
+    /// number of times the target went down
+    pub outages: usize,
+    /// time spent down in finished outages, see `downtime`
+    past_downtime: Duration,
+    /// the state changed too often recently
+    pub flapping: bool,
+    losses: usize,
+    replies: usize,
+    /// times of the recent state changes
+    changes: VecDeque<DateTime<Local>>,
+}
+
+/// a change of the state of a target
+#[derive(Debug, Clone)]
+pub struct StatusEvent {
+    /// display name of the target
+    pub target: String,
+    /// the address that was probed
+    pub ip: String,
+    /// when the state changed
+    pub time: DateTime<Local>,
+    /// the state before the change
+    pub from: Status,
+    /// the state after the change
+    pub to: Status,
+    /// how long the target was in the previous state, the outage when it comes back up
+    pub duration: Duration,
+    /// the target changed its state too often recently
+    pub flapping: bool,
+}
+
+impl StatusTracker {
+    /// record a probe, returning the previous state and how long it lasted when the state changed,
+    /// `thresholds` tell how many probes in a row take the target down and up
+    pub fn probe(&mut self, reply: bool, health: Health, thresholds: &Thresholds, now: DateTime<Local>) -> Option<(Status, Duration)> {
+        if reply {
+            self.replies += 1;
+            self.losses = 0;
+        } else {
+            self.losses += 1;
+            self.replies = 0;
+        }
+
+        let status = if self.losses >= thresholds.down_after {
+            Status::Down
+        } else if (self.status == Status::Down && self.replies < thresholds.up_after) || (self.status == Status::Unknown && !reply) {
+            self.status
+        } else if health == Health::Ok {
+            Status::Up
+        } else {
+            Status::Degraded
+        };
+
+        while self.changes.front().is_some_and(|&time| elapsed(time, now) > FLAP_WINDOW) {
+            self.changes.pop_front();
+        }
+        if status == self.status {
+            self.flapping = self.changes.len() >= FLAP_CHANGES;
+            return None;
+        }
+
+        let from = std::mem::replace(&mut self.status, status);
+        let duration = self.since.map(|since| elapsed(since, now)).unwrap_or_default();
+        self.since = Some(now);
+        if from == Status::Down {
+            self.past_downtime += duration;
+        }
+        if status == Status::Down {
+            self.outages += 1;
+        }
+        // coming up for the first time is not worth an event
+        if from == Status::Unknown && status != Status::Down {
+            return None;
+        }
+        self.changes.push_back(now);
+        self.flapping = self.changes.len() >= FLAP_CHANGES;
+        Some((from, duration))
+    }
+
+    /// total time spent down, including the current outage
+    pub fn downtime(&self, now: DateTime<Local>) -> Duration {
+        match (self.status, self.since) {
+            (Status::Down, Some(since)) => self.past_downtime + elapsed(since, now),
+            _ => self.past_downtime,
+        }
+    }
+
+    /// how long the target has been in its current state
+    pub fn duration(&self, now: DateTime<Local>) -> Duration {
+        self.since.map(|since| elapsed(since, now)).unwrap_or_default()
+    }
+}
+
+fn elapsed(since: DateTime<Local>, now: DateTime<Local>) -> Duration {
+    (now - since).to_std().unwrap_or_default()
+}
+
+/// update the state of a target after a probe was recorded at `now`, logging the change
+pub(crate) fn update_status(data: &mut IpData, thresholds: &Thresholds, events: &mut EventLog, now: DateTime<Local>) {
+    let thresholds = thresholds.for_target(&data.options);
+    let health = thresholds.target(data);
+    if let Some((from, duration)) = data.status.probe(data.last_attr >= 0.0, health, &thresholds, now) {
+        events.push(StatusEvent {
+            target: data.name().to_string(),
+            ip: data.ip.clone(),
+            time: now,
+            from,
+            to: data.status.status,
+            duration,
+            flapping: data.status.flapping,
+        });
+    }
+}
+
+/// bounded log of the state changes of all targets, optionally appended to a csv file
+#[derive(Debug, Default)]
+pub struct EventLog {
+    records: VecDeque<StatusEvent>,
+    /// total number of events ever recorded, including dropped ones
+    total: usize,
+    file: Option<File>,
+}
+
+impl EventLog {
+    /// an event log that also appends every event to a csv file
+    pub fn with_file(path: &Path) -> Result<Self, String> {
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map_err(|err| format!("failed to open event log {}: {}", path.display(), err))?;
+        let empty = file.metadata().map(|metadata| metadata.len() == 0).unwrap_or(false);
+        if empty {
+            writeln!(file, "time,target,ip,from,to,duration_secs,flapping")
+                .map_err(|err| format!("failed to write event log {}: {}", path.display(), err))?;
+        }
+        Ok(Self {
+            file: Some(file),
+            ..Default::default()
+        })
+    }
+
+    /// keep an event and append it to the log file, dropping the oldest one when the log is full
+    pub fn push(&mut self, event: StatusEvent) {
+        if let Some(file) = self.file.as_mut() {
+            // a full disk must not stop the monitoring
+            let _ = writeln!(
+                file,
+                "{},{},{},{},{},{:.0},{}",
+                event.time.to_rfc3339(),
+                csv_field(&event.target),
+                event.ip,
+                event.from,
+                event.to,
+                event.duration.as_secs_f64(),
+                event.flapping
+            );
+        }
+        self.records.push_back(event);
+        self.total += 1;
+        if self.records.len() > EVENT_LOG_SIZE {
+            self.records.pop_front();
+        }
+    }
+
+    /// number of events ever pushed, including dropped ones
+    pub fn total(&self) -> usize {
+        self.total
+    }
+
+    /// events from newest to oldest
+    pub fn recent(&self) -> impl Iterator<Item = &StatusEvent> {
+        self.records.iter().rev()
+    }
+}
+
+// quote a field that would break the csv line
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// a duration like 1h 2m 3s, 5m 0s or 42s
+pub fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    match (secs / 3600, secs % 3600 / 60, secs % 60) {
+        (0, 0, s) => format!("{}s", s),
+        (0, m, s) => format!("{}m {}s", m, s),
+        (h, m, s) => format!("{}h {}m {}s", h, m, s),
+    }
+}
@@ -0,0 +1,432 @@
+use std::collections::HashSet;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::path::{Path, PathBuf};
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+use std::time::{Duration, SystemTime};
+use serde::{Deserialize, Serialize};
+use crate::probe::{ProbeKind, SimOptions, SIM_SCHEME};
+
+/// how often a watched target file is checked for changes
+pub const WATCH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// largest number of targets a single subnet or range may expand to
+pub const MAX_EXPANDED_TARGETS: usize = 1024;
+
+/// expand a subnet (`10.0.0.0/28`, `2001:db8::/120`) or range (`192.168.1.10-20`,
+/// `10.0.0.250-10.0.1.5`) into its addresses, any other target is returned as is
+pub fn expand_target(target: &str) -> Result<Vec<String>, String> {
+    // a simulated target is a single row, its settings are checked up front
+    if let Some(settings) = target.strip_prefix(SIM_SCHEME) {
+        settings.parse::<SimOptions>().map_err(|err| format!("{}: {}", target, err))?;
+        return Ok(vec![target.to_string()]);
+    }
+    if let Some((addr, prefix)) = target.split_once('/') {
+        if let Ok(addr) = addr.parse::<IpAddr>() {
+            let prefix = prefix
+                .parse::<u32>()
+                .map_err(|_| format!("invalid prefix length in {}", target))?;
+            return expand_subnet(target, addr, prefix);
+        }
+    }
+    if let Some((start, end)) = target.split_once('-') {
+        if let Ok(start) = start.parse::<IpAddr>() {
+            return expand_range(target, start, end);
+        }
+    }
+    Ok(vec![target.to_string()])
+}
+
+fn expand_subnet(target: &str, addr: IpAddr, prefix: u32) -> Result<Vec<String>, String> {
+    let bits = match addr {
+        IpAddr::V4(_) => 32,
+        IpAddr::V6(_) => 128,
+    };
+    if prefix > bits {
+        return Err(format!("invalid prefix length in {}", target));
+    }
+    let host_bits = bits - prefix;
+    if host_bits > 64 || (1u128 << host_bits) > (MAX_EXPANDED_TARGETS as u128) + 2 {
+        return Err(too_many(target));
+    }
+
+    let mask = if host_bits == bits { 0 } else { u128::MAX << host_bits };
+    let first = to_u128(addr) & mask;
+    let last = first + (1u128 << host_bits) - 1;
+    // the network and broadcast addresses of an ipv4 subnet are not hosts
+    let (first, last) = match addr {
+        IpAddr::V4(_) if host_bits > 1 => (first + 1, last - 1),
+        _ => (first, last),
+    };
+    if last - first + 1 > MAX_EXPANDED_TARGETS as u128 {
+        return Err(too_many(target));
+    }
+    Ok((first..=last).map(|value| from_u128(addr, value).to_string()).collect())
+}
+
+fn expand_range(target: &str, start: IpAddr, end: &str) -> Result<Vec<String>, String> {
+    let end = match (start, end.parse::<IpAddr>()) {
+        (IpAddr::V4(_), Ok(end @ IpAddr::V4(_))) | (IpAddr::V6(_), Ok(end @ IpAddr::V6(_))) => end,
+        (IpAddr::V4(start_v4), _) => {
+            // a short ipv4 range only gives the last octet of the end address
+            let last = end
+                .parse::<u8>()
+                .map_err(|_| format!("invalid end of range in {}", target))?;
+            let [a, b, c, _] = start_v4.octets();
+            IpAddr::V4(Ipv4Addr::new(a, b, c, last))
+        }
+        (IpAddr::V6(_), _) => return Err(format!("invalid end of range in {}", target)),
+    };
+
+    let (first, last) = (to_u128(start), to_u128(end));
+    if last < first {
+        return Err(format!("range {} ends before it starts", target));
+    }
+    if last - first >= MAX_EXPANDED_TARGETS as u128 {
+        return Err(too_many(target));
+    }
+    Ok((first..=last).map(|value| from_u128(start, value).to_string()).collect())
+}
+
+fn too_many(target: &str) -> String {
+    format!("{} expands to more than {} targets", target, MAX_EXPANDED_TARGETS)
+}
+
+fn to_u128(addr: IpAddr) -> u128 {
+    match addr {
+        IpAddr::V4(addr) => u32::from(addr) as u128,
+        IpAddr::V6(addr) => u128::from(addr),
+    }
+}
+
+// an address of the same family as `like`
+fn from_u128(like: IpAddr, value: u128) -> IpAddr {
+    match like {
+        IpAddr::V4(_) => IpAddr::V4(Ipv4Addr::from(value as u32)),
+        IpAddr::V6(_) => IpAddr::V6(Ipv6Addr::from(value)),
+    }
+}
+
+/// settings of a single target that override the command line
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TargetOptions {
+    /// how the target is probed, icmp when not set
+    pub probe: Option<ProbeKind>,
+    /// port of tcp, udp and http probes
+    pub port: Option<u16>,
+    /// time between two probes
+    pub interval: Option<Duration>,
+    /// rtt above which the target is a warning, in milliseconds
+    pub warn_rtt: Option<f64>,
+    /// rtt above which the target is critical, in milliseconds
+    pub crit_rtt: Option<f64>,
+    /// loss above which the target is a warning, in percent
+    pub warn_loss: Option<f64>,
+    /// loss above which the target is critical, in percent
+    pub crit_loss: Option<f64>,
+    /// timeouts in a row after which the target is down
+    pub down_after: Option<usize>,
+    /// replies in a row after which the target is up again
+    pub up_after: Option<usize>,
+}
+
+impl TargetOptions {
+    // set one `key=value` option of a target file line
+    fn set(&mut self, key: &str, value: &str) -> Result<(), String> {
+        match key {
+            "probe" => self.probe = Some(value.parse()?),
+            "port" => self.port = Some(value.parse().map_err(|_| format!("invalid port: {}", value))?),
+            "interval" => self.interval = Some(parse_duration(value)?),
+            "warn-rtt" | "warn_rtt" => self.warn_rtt = Some(parse_rtt(value)?),
+            "crit-rtt" | "crit_rtt" => self.crit_rtt = Some(parse_rtt(value)?),
+            "warn-loss" | "warn_loss" => self.warn_loss = Some(parse_loss(value)?),
+            "crit-loss" | "crit_loss" => self.crit_loss = Some(parse_loss(value)?),
+            "down-after" | "down_after" => self.down_after = Some(parse_probes(value)?),
+            "up-after" | "up_after" => self.up_after = Some(parse_probes(value)?),
+            _ => return Err(format!("unknown target option: {}", key)),
+        }
+        Ok(())
+    }
+
+    // reject options that cannot be honoured
+    /// check that the options fit together, like a port for tcp probes
+    pub fn validate(&self) -> Result<(), String> {
+        if self.down_after == Some(0) || self.up_after == Some(0) {
+            return Err("down-after and up-after must be at least 1".to_string());
+        }
+        let probe = self.probe.unwrap_or_default();
+        match self.port {
+            Some(_) if !probe.uses_port() => Err(format!("port has no effect on {} probes", probe)),
+            None if probe.uses_port() && probe.default_port().is_none() => Err(format!("{} probes need a port", probe)),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// parse a duration like 500ms, 30s, 5m or 1h, a bare number is seconds
+pub fn parse_duration(value: &str) -> Result<Duration, String> {
+    let value = value.trim();
+    let split = value.find(|c: char| !c.is_ascii_digit() && c != '.').unwrap_or(value.len());
+    let (number, unit) = value.split_at(split);
+    let number: f64 = number.parse().map_err(|_| format!("invalid duration: {}", value))?;
+    let secs = match unit {
+        "ms" => number / 1000.0,
+        "" | "s" => number,
+        "m" => number * 60.0,
+        "h" => number * 3600.0,
+        _ => return Err(format!("invalid duration unit: {}", unit)),
+    };
+    if secs <= 0.0 {
+        return Err(format!("duration must be positive: {}", value));
+    }
+    Ok(Duration::from_secs_f64(secs))
+}
+
+/// an rtt threshold like 50ms or 0.2s, in milliseconds
+pub fn parse_rtt(value: &str) -> Result<f64, String> {
+    Ok(parse_duration(value)?.as_secs_f64() * 1000.0)
+}
+
+/// a loss threshold like 5% or 5, in percent
+pub fn parse_loss(value: &str) -> Result<f64, String> {
+    let loss: f64 = value
+        .trim_end_matches('%')
+        .parse()
+        .map_err(|_| format!("invalid loss: {}", value))?;
+    if !(0.0..=100.0).contains(&loss) {
+        return Err(format!("loss must be between 0 and 100%: {}", value));
+    }
+    Ok(loss)
+}
+
+/// a number of probes in a row like down-after=3, at least one
+pub fn parse_probes(value: &str) -> Result<usize, String> {
+    match value.trim().parse() {
+        Ok(0) => Err(format!("must be at least 1 probe: {}", value)),
+        Ok(probes) => Ok(probes),
+        Err(_) => Err(format!("invalid number of probes: {}", value)),
+    }
+}
+
+/// a target with the settings it was given in a target file
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TargetSpec {
+    /// hostname or ip address to ping
+    pub host: String,
+    /// name shown instead of the host
+    pub label: Option<String>,
+    /// the group the target is shown in
+    pub group: Option<String>,
+    /// settings that override the command line
+    pub options: TargetOptions,
+}
+
+impl TargetSpec {
+    /// a target without a label, group or options
+    pub fn new(host: impl Into<String>) -> Self {
+        Self {
+            host: host.into(),
+            ..Self::default()
+        }
+    }
+
+    /// a target given as `host` or `group:host`
+    pub fn parse(target: &str) -> Self {
+        if target.parse::<IpAddr>().is_err() {
+            if let Some((group, host)) = target.split_once(':') {
+                if !group.is_empty() && !host.is_empty() && !group.contains('/') && !host.starts_with("//") {
+                    return Self {
+                        group: Some(group.to_string()),
+                        ..Self::new(host)
+                    };
+                }
+            }
+        }
+        Self::new(target)
+    }
+
+    /// expand a subnet or range into one spec per address, sharing the group and options
+    pub fn expand(self) -> Result<Vec<TargetSpec>, String> {
+        let hosts = expand_target(&self.host)?;
+        if hosts.len() == 1 && hosts[0] == self.host {
+            return Ok(vec![self]);
+        }
+        Ok(hosts
+            .into_iter()
+            .map(|host| TargetSpec {
+                host,
+                label: None,
+                ..self.clone()
+            })
+            .collect())
+    }
+}
+
+/// expand subnets and ranges into single addresses and drop duplicate hosts,
+/// the original order is still preserved
+pub fn expand_targets(targets: Vec<TargetSpec>) -> Result<Vec<TargetSpec>, String> {
+    let mut expanded = Vec::new();
+    for target in targets {
+        expanded.extend(target.expand()?);
+    }
+    let mut seen = HashSet::new();
+    Ok(expanded.into_iter().filter(|target| seen.insert(target.host.clone())).collect())
+}
+
+/// read a target file, `.toml` files use the toml format and anything else the line format:
+///
+/// ```text
+/// # comments start with #
+/// [dc-east]                        # a section puts the following targets into a group
+/// 10.0.0.1                         # a bare host
+/// core-router-1 10.0.0.2           # a label and a host
+/// web 10.0.0.3 interval=2s warn-rtt=50ms crit-loss=20%
+/// ```
+pub fn read_target_file(path: &Path) -> Result<Vec<TargetSpec>, String> {
+    let content = std::fs::read_to_string(path)
+        .map_err(|err| format!("failed to read target file {}: {}", path.display(), err))?;
+    let specs = if path.extension().is_some_and(|ext| ext == "toml") {
+        parse_toml_targets(&content)
+    } else {
+        parse_target_lines(&content)
+    };
+    specs.map_err(|err| format!("{}: {}", path.display(), err))
+}
+
+/// read targets from stdin in the line format of a target file
+pub fn read_target_stdin() -> Result<Vec<TargetSpec>, String> {
+    let content = std::io::read_to_string(std::io::stdin())
+        .map_err(|err| format!("failed to read targets from stdin: {}", err))?;
+    parse_target_lines(&content).map_err(|err| format!("stdin: {}", err))
+}
+
+fn parse_target_lines(content: &str) -> Result<Vec<TargetSpec>, String> {
+    let mut specs = Vec::new();
+    let mut group = None;
+    for (number, line) in content.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or_default().trim();
+        if line.is_empty() {
+            continue;
+        }
+        if let Some(section) = line.strip_prefix('[').and_then(|line| line.strip_suffix(']')) {
+            let section = section.trim();
+            group = (!section.is_empty()).then(|| section.to_string());
+            continue;
+        }
+        let spec = parse_target_line(line, group.clone()).map_err(|err| format!("line {}: {}", number + 1, err))?;
+        specs.push(spec);
+    }
+    Ok(specs)
+}
+
+fn parse_target_line(line: &str, group: Option<String>) -> Result<TargetSpec, String> {
+    let mut options = TargetOptions::default();
+    let mut label_option = None;
+    let mut words = Vec::new();
+    for word in line.split_whitespace() {
+        // a url like `sim://lat=20ms` is a host, its settings are no target options
+        if word.contains("://") {
+            words.push(word);
+            continue;
+        }
+        match word.split_once('=') {
+            Some(("label", value)) => label_option = Some(value.to_string()),
+            Some((key, value)) => options.set(key, value)?,
+            None => words.push(word),
+        }
+    }
+    options.validate()?;
+    let (label, host) = match words[..] {
+        [host] => (label_option, host),
+        [_, _] if label_option.is_some() => return Err("the label is given twice".to_string()),
+        [label, host] => (Some(label.to_string()), host),
+        [] => return Err("missing host".to_string()),
+        _ => return Err(format!("expected a host or a label and a host, got: {}", words.join(" "))),
+    };
+    Ok(TargetSpec {
+        host: host.to_string(),
+        label,
+        group,
+        options,
+    })
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TomlTargets {
+    #[serde(default)]
+    target: Vec<TomlTarget>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TomlTarget {
+    host: String,
+    label: Option<String>,
+    group: Option<String>,
+    probe: Option<String>,
+    port: Option<u16>,
+    interval: Option<String>,
+    warn_rtt: Option<String>,
+    crit_rtt: Option<String>,
+    warn_loss: Option<String>,
+    crit_loss: Option<String>,
+    down_after: Option<usize>,
+    up_after: Option<usize>,
+}
+
+// the toml format is a list of `[[target]]` tables with the same settings as the line format
+fn parse_toml_targets(content: &str) -> Result<Vec<TargetSpec>, String> {
+    let file: TomlTargets = toml::from_str(content).map_err(|err| err.to_string())?;
+    file.target
+        .into_iter()
+        .map(|target| {
+            let options = TargetOptions {
+                probe: target.probe.as_deref().map(str::parse).transpose()?,
+                port: target.port,
+                interval: target.interval.as_deref().map(parse_duration).transpose()?,
+                warn_rtt: target.warn_rtt.as_deref().map(parse_rtt).transpose()?,
+                crit_rtt: target.crit_rtt.as_deref().map(parse_rtt).transpose()?,
+                warn_loss: target.warn_loss.as_deref().map(parse_loss).transpose()?,
+                crit_loss: target.crit_loss.as_deref().map(parse_loss).transpose()?,
+                down_after: target.down_after,
+                up_after: target.up_after,
+            };
+            options.validate().map_err(|err| format!("{}: {}", target.host, err))?;
+            Ok(TargetSpec {
+                host: target.host,
+                label: target.label,
+                group: target.group,
+                options,
+            })
+        })
+        .collect()
+}
+
+/// send the expanded targets of the file every time it changes on disk, until `running` is false
+pub fn watch_target_file(
+    path: PathBuf,
+    running: Arc<Mutex<bool>>,
+    tx: mpsc::Sender<Result<Vec<TargetSpec>, String>>,
+) {
+    let modified = |path: &Path| -> Option<(SystemTime, u64)> {
+        let metadata = std::fs::metadata(path).ok()?;
+        Some((metadata.modified().ok()?, metadata.len()))
+    };
+    thread::spawn(move || {
+        let mut last = modified(&path);
+        while *running.lock().unwrap() {
+            thread::sleep(WATCH_INTERVAL);
+            let current = modified(&path);
+            // a file that is being rewritten may be missing for a moment
+            if current.is_none() || current == last {
+                continue;
+            }
+            last = current;
+            let targets = read_target_file(&path).and_then(expand_targets);
+            if tx.send(targets).is_err() {
+                break;
+            }
+        }
+    });
+}
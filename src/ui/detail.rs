@@ -0,0 +1,315 @@
+use ratatui::{symbols, Frame};
+use ratatui::layout::{Constraint, Direction, Layout, Rect};
+use ratatui::prelude::{Color, Line, Modifier, Span, Style};
+use ratatui::widgets::{Axis, Block, Borders, Chart, Dataset, GraphType, Paragraph, Wrap};
+use nping::ip_data::IpData;
+use nping::error_log::ErrorLog;
+use nping::stats::calculate_loss_pkg;
+use crate::ui::utils::{display_ip, error_line, health_color, status_color};
+use nping::status::format_duration;
+use nping::health::Thresholds;
+use chrono::{DateTime, Local};
+
+/// full screen detail pane of a single target
+pub fn draw_detail_view(
+    f: &mut Frame,
+    data: &IpData,
+    errs: &ErrorLog,
+    thresholds: &Thresholds,
+    now: DateTime<Local>,
+    area: Rect,
+) {
+    let thresholds = thresholds.for_target(&data.options);
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(1),
+            Constraint::Length(8),
+            Constraint::Min(10),
+            Constraint::Length(12),
+        ].as_ref())
+        .split(area);
+
+    let title = Line::from(vec![
+        Span::styled(" 🏎  Nping Detail ", Style::default().fg(Color::Cyan)),
+        Span::styled(format!("{} ", data.name()), Style::default().fg(Color::Green).add_modifier(Modifier::BOLD)),
+        Span::raw(match data.label {
+            Some(_) if data.addr != data.ip => format!("({} {})", data.addr, display_ip(data)),
+            _ => format!("({})", display_ip(data)),
+        }),
+        Span::raw("  press Enter or Esc to close"),
+    ]);
+    f.render_widget(Paragraph::new(title), chunks[0]);
+
+    let top_chunks = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(50), Constraint::Percentage(20), Constraint::Percentage(30)].as_ref())
+        .split(chunks[1]);
+    draw_statistics(f, data, &thresholds, now, top_chunks[0]);
+    draw_addresses(f, data, top_chunks[1]);
+    draw_dns_changes(f, data, top_chunks[2]);
+
+    draw_history_chart(f, data, chunks[2]);
+
+    let bottom_chunks = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(40), Constraint::Percentage(60)].as_ref())
+        .split(chunks[3]);
+    draw_probe_log(f, data, &thresholds, bottom_chunks[0]);
+    draw_target_errors(f, data, errs, bottom_chunks[1]);
+}
+
+fn metric(name: &str, value: String, color: Color) -> Vec<Span<'static>> {
+    vec![
+        Span::raw(format!("{}: ", name)),
+        Span::styled(value, Style::default().fg(color)),
+        Span::raw("  "),
+    ]
+}
+
+// the state of the target and its outages
+fn target_status(data: &IpData, now: DateTime<Local>) -> Vec<Span<'static>> {
+    let status = &data.status;
+    let mut spans = [
+        metric("Status", format!("{} for {}", status.status, format_duration(status.duration(now))), status_color(status.status)),
+        metric("Outages", status.outages.to_string(), Color::Green),
+        metric("Downtime", format_duration(status.downtime(now)), Color::Green),
+    ].concat();
+    if status.flapping {
+        spans.push(Span::styled("flapping", Style::default().fg(Color::Magenta).add_modifier(Modifier::BOLD)));
+    }
+    spans
+}
+
+// the group and target file settings of the target
+fn target_settings(data: &IpData) -> Vec<Span<'static>> {
+    let options = &data.options;
+    let settings = [
+        ("Group", data.group.clone()),
+        ("Probe", options.probe.map(|probe| probe.to_string())),
+        ("Port", options.port.map(|port| port.to_string())),
+        ("Interval", options.interval.map(|interval| format!("{:?}", interval))),
+        ("Warn rtt", options.warn_rtt.map(|rtt| format!("{}ms", rtt))),
+        ("Crit rtt", options.crit_rtt.map(|rtt| format!("{}ms", rtt))),
+        ("Warn loss", options.warn_loss.map(|loss| format!("{}%", loss))),
+        ("Crit loss", options.crit_loss.map(|loss| format!("{}%", loss))),
+    ];
+    settings
+        .into_iter()
+        .filter_map(|(name, value)| value.map(|value| metric(name, value, Color::Cyan)))
+        .flatten()
+        .collect()
+}
+
+fn draw_statistics(f: &mut Frame, data: &IpData, thresholds: &Thresholds, now: DateTime<Local>, area: Rect) {
+    let loss_pkg = calculate_loss_pkg(data.timeout, data.received);
+    let loss_pkg_color = health_color(thresholds.loss(loss_pkg));
+    let last = if data.last_attr == 0.0 {
+        "< 0.01ms".to_string()
+    } else if data.last_attr == -1.0 {
+        "timeout".to_string()
+    } else {
+        format!("{:.2}ms", data.last_attr)
+    };
+
+    let lines = vec![
+        Line::from([
+            metric("Sent", (data.received + data.timeout).to_string(), Color::Green),
+            metric("Received", data.received.to_string(), Color::Green),
+            metric("Timeout", data.timeout.to_string(), Color::Green),
+            metric("Loss", format!("{:.2}%", loss_pkg), loss_pkg_color),
+        ].concat()),
+        Line::from([
+            metric("Last", last, Color::Green),
+            metric("Avg", format!("{:.2}ms", data.session_avg_rtt()), health_color(thresholds.session_avg_rtt(data))),
+            metric("Jitter", format!("{:.2}ms", data.session_jitter()), Color::Green),
+        ].concat()),
+        Line::from([
+            metric("Min", format!("{:.2}ms", data.min_rtt), Color::Green),
+            metric("Max", format!("{:.2}ms", data.max_rtt), Color::Green),
+        ].concat()),
+        Line::from(percentiles(data)),
+        Line::from(target_status(data, now)),
+        Line::from(target_settings(data)),
+    ];
+
+    let paragraph = Paragraph::new(lines)
+        .block(Block::default().title("Statistics").borders(Borders::ALL))
+        .wrap(Wrap { trim: true });
+    f.render_widget(paragraph, area);
+}
+
+// percentiles of the kept history, which is the whole session until HISTORY_SIZE probes were sent
+fn percentiles(data: &IpData) -> Vec<Span<'static>> {
+    let mut spans = [
+        metric("P50", format!("{:.2}ms", data.history_percentile(50.0)), Color::Green),
+        metric("P95", format!("{:.2}ms", data.history_percentile(95.0)), Color::Green),
+        metric("P99", format!("{:.2}ms", data.history_percentile(99.0)), Color::Green),
+    ].concat();
+    if data.history.len() < data.received + data.timeout {
+        spans.push(Span::styled(format!("(last {} probes)", data.history.len()), Style::default().fg(Color::DarkGray)));
+    }
+    spans
+}
+
+fn draw_addresses(f: &mut Frame, data: &IpData, area: Rect) {
+    let lines: Vec<Line> = data
+        .addresses
+        .iter()
+        .map(|addr| {
+            if *addr == data.ip {
+                Line::from(vec![
+                    Span::styled(addr.clone(), Style::default().fg(Color::Green)),
+                    Span::raw(" (probing)"),
+                ])
+            } else {
+                Line::from(addr.clone())
+            }
+        })
+        .collect();
+
+    let paragraph = Paragraph::new(lines)
+        .block(Block::default().title("Resolved Addresses").borders(Borders::ALL));
+    f.render_widget(paragraph, area);
+}
+
+fn draw_dns_changes(f: &mut Frame, data: &IpData, area: Rect) {
+    let lines: Vec<Line> = if data.dns_changes.is_empty() {
+        vec![Line::from(Span::styled("no address changes", Style::default().fg(Color::DarkGray)))]
+    } else {
+        data.dns_changes
+            .iter()
+            .rev()
+            .map(|change| {
+                Line::from(vec![
+                    Span::raw(format!("{} ", change.time.format("%H:%M:%S"))),
+                    Span::raw(format!("#{} ", change.seq + 1)),
+                    Span::raw(format!("{} → ", change.old_ip)),
+                    Span::styled(change.new_ip.clone(), Style::default().fg(Color::Yellow)),
+                ])
+            })
+            .collect()
+    };
+
+    let paragraph = Paragraph::new(lines)
+        .block(Block::default().title("DNS History").borders(Borders::ALL));
+    f.render_widget(paragraph, area);
+}
+
+fn draw_history_chart(f: &mut Frame, data: &IpData, area: Rect) {
+    // only show as many probes as fit the width of the chart
+    let max_points = (area.width as usize).saturating_sub(12).max(2);
+    let records: Vec<_> = data.history.iter().rev().take(max_points).rev().collect();
+
+    let replies = records
+        .iter()
+        .filter(|r| r.rtt >= 0.0)
+        .map(|r| (r.seq as f64, r.rtt))
+        .collect::<Vec<(f64, f64)>>();
+    let timeouts = records
+        .iter()
+        .filter(|r| r.rtt < 0.0)
+        .map(|r| (r.seq as f64, 0.0))
+        .collect::<Vec<(f64, f64)>>();
+
+    let first = records.first().map(|r| r.seq).unwrap_or(0) as f64;
+    let last = records.last().map(|r| r.seq).unwrap_or(0) as f64;
+    let x_bounds = [first, last.max(first + 1.0)];
+    let max_rtt = records.iter().map(|r| r.rtt).fold(0.0, f64::max);
+    let y_bounds = [0.0, if max_rtt > 0.0 { max_rtt * 1.2 } else { 1.0 }];
+
+    // vertical markers where the target was re-resolved to a new address
+    let dns_change_lines = data
+        .dns_changes
+        .iter()
+        .map(|change| change.seq as f64 + 0.5)
+        .filter(|x| *x >= x_bounds[0] && *x <= x_bounds[1])
+        .map(|x| vec![(x, y_bounds[0]), (x, y_bounds[1])])
+        .collect::<Vec<Vec<(f64, f64)>>>();
+
+    let mut datasets = vec![
+        Dataset::default()
+            .name("rtt")
+            .marker(symbols::Marker::Braille)
+            .style(Style::default().fg(Color::Green))
+            .graph_type(GraphType::Line)
+            .data(&replies),
+        Dataset::default()
+            .name("timeout")
+            .marker(symbols::Marker::Dot)
+            .style(Style::default().fg(Color::Red))
+            .graph_type(GraphType::Scatter)
+            .data(&timeouts),
+    ];
+    for line in &dns_change_lines {
+        datasets.push(Dataset::default()
+            .marker(symbols::Marker::Braille)
+            .style(Style::default().fg(Color::Yellow))
+            .graph_type(GraphType::Line)
+            .data(line));
+    }
+
+    let chart = Chart::new(datasets)
+        .block(Block::default().title("Rtt History").borders(Borders::ALL))
+        .legend_position(None)
+        .x_axis(
+            Axis::default()
+                .title("count")
+                .bounds(x_bounds)
+                .labels(vec![
+                    Span::raw(format!("{}", x_bounds[0])),
+                    Span::raw(format!("{}", x_bounds[1])),
+                ]),
+        )
+        .y_axis(
+            Axis::default()
+                .title("rtt")
+                .bounds(y_bounds)
+                .labels(
+                    (0..=5)
+                        .map(|i| Span::raw(format!("{:.2}ms", i as f64 * (y_bounds[1] / 5.0))))
+                        .collect::<Vec<Span>>(),
+                ),
+        );
+    f.render_widget(chart, area);
+}
+
+fn draw_probe_log(f: &mut Frame, data: &IpData, thresholds: &Thresholds, area: Rect) {
+    let lines: Vec<Line> = data
+        .history
+        .iter()
+        .rev()
+        .take(area.height.saturating_sub(2) as usize)
+        .map(|record| {
+            let text = if record.rtt < 0.0 {
+                "timeout".to_string()
+            } else {
+                format!("{}ms", record.rtt)
+            };
+            let color = health_color(thresholds.rtt(record.rtt, data.max_rtt));
+            Line::from(vec![
+                Span::raw(format!("{} ", record.time.format("%H:%M:%S%.3f"))),
+                Span::raw(format!("#{} ", record.seq)),
+                Span::raw(format!("{} ", record.ip)),
+                Span::styled(text, Style::default().fg(color)),
+            ])
+        })
+        .collect();
+
+    let paragraph = Paragraph::new(lines)
+        .block(Block::default().title("Recent Probes").borders(Borders::ALL));
+    f.render_widget(paragraph, area);
+}
+
+fn draw_target_errors(f: &mut Frame, data: &IpData, errs: &ErrorLog, area: Rect) {
+    let lines: Vec<Line> = errs
+        .for_target(data.id)
+        .take(area.height.saturating_sub(2) as usize)
+        .map(error_line)
+        .collect();
+
+    let paragraph = Paragraph::new(lines)
+        .block(Block::default().title("Errors").borders(Borders::ALL))
+        .wrap(Wrap { trim: true });
+    f.render_widget(paragraph, area);
+}
@@ -0,0 +1,42 @@
+use ratatui::Frame;
+use ratatui::layout::{Constraint, Direction, Layout, Rect};
+use ratatui::prelude::{Color, Line, Span, Style};
+use ratatui::widgets::{Block, Borders, Paragraph, Wrap};
+use nping::error_log::{ErrorLog, ERROR_LOG_SIZE};
+use crate::ui::utils::error_line;
+
+/// full screen log of the errors of all targets, newest first
+pub fn draw_error_log_view(
+    f: &mut Frame,
+    errs: &ErrorLog,
+    area: Rect,
+) {
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(1),
+            Constraint::Min(3),
+        ].as_ref())
+        .split(area);
+
+    let title = Line::from(vec![
+        Span::styled(" 🏎  Nping Error Log ", Style::default().fg(Color::Cyan)),
+        Span::raw(format!(
+            "{} errors, the last {} are kept  press e or Esc to close",
+            errs.total(),
+            ERROR_LOG_SIZE
+        )),
+    ]);
+    f.render_widget(Paragraph::new(title), chunks[0]);
+
+    let lines: Vec<Line> = errs
+        .recent()
+        .take(chunks[1].height.saturating_sub(2) as usize)
+        .map(error_line)
+        .collect();
+
+    let paragraph = Paragraph::new(lines)
+        .block(Block::default().title("🚨Errors").borders(Borders::ALL))
+        .wrap(Wrap { trim: true });
+    f.render_widget(paragraph, chunks[1]);
+}
@@ -0,0 +1,101 @@
+use chrono::{DateTime, Local};
+use ratatui::Frame;
+use ratatui::layout::{Constraint, Direction, Layout, Rect};
+use ratatui::prelude::{Color, Line, Modifier, Span, Style};
+use ratatui::widgets::{Block, Borders, Paragraph};
+use nping::ip_data::IpData;
+use nping::status::{format_duration, EventLog, Status, StatusEvent, EVENT_LOG_SIZE};
+use crate::ui::utils::status_color;
+
+/// full screen log of the state changes of all targets with their outages, newest first
+pub fn draw_event_log_view(
+    f: &mut Frame,
+    ip_data: &[IpData],
+    events: &EventLog,
+    now: DateTime<Local>,
+    area: Rect,
+) {
+    let summary_height = (ip_data.len() as u16 + 2).min(area.height / 2);
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(1),
+            Constraint::Length(summary_height),
+            Constraint::Min(3),
+        ].as_ref())
+        .split(area);
+
+    let outages: usize = ip_data.iter().map(|data| data.status.outages).sum();
+    let title = Line::from(vec![
+        Span::styled(" 🏎  Nping Event Log ", Style::default().fg(Color::Cyan)),
+        Span::raw(format!(
+            "{} outages, {} events, the last {} are kept  press l or Esc to close",
+            outages,
+            events.total(),
+            EVENT_LOG_SIZE
+        )),
+    ]);
+    f.render_widget(Paragraph::new(title), chunks[0]);
+
+    // the targets that were down the longest first
+    let mut targets: Vec<&IpData> = ip_data.iter().collect();
+    targets.sort_by_key(|data| std::cmp::Reverse((data.status.downtime(now), data.status.outages)));
+    let name_width = ip_data.iter().map(|data| data.name().chars().count()).max().unwrap_or(0);
+    let lines: Vec<Line> = targets
+        .into_iter()
+        .take(chunks[1].height.saturating_sub(2) as usize)
+        .map(|data| {
+            let status = &data.status;
+            let mut spans = vec![
+                Span::styled(format!("{:<width$}  ", data.name(), width = name_width), Style::default().fg(Color::Cyan)),
+                Span::styled(format!("{:<8}", status.status), Style::default().fg(status_color(status.status))),
+                Span::raw(format!(" for {:<10}", format_duration(status.duration(now)))),
+                Span::raw(format!(" outages: {:<4}", status.outages)),
+                Span::raw(format!(" downtime: {}", format_duration(status.downtime(now)))),
+            ];
+            if status.flapping {
+                spans.push(Span::styled("  flapping", Style::default().fg(Color::Magenta).add_modifier(Modifier::BOLD)));
+            }
+            Line::from(spans)
+        })
+        .collect();
+    let paragraph = Paragraph::new(lines)
+        .block(Block::default().title("Targets").borders(Borders::ALL));
+    f.render_widget(paragraph, chunks[1]);
+
+    let lines: Vec<Line> = if events.total() == 0 {
+        vec![Line::from(Span::styled("no state changes yet", Style::default().fg(Color::DarkGray)))]
+    } else {
+        events
+            .recent()
+            .take(chunks[2].height.saturating_sub(2) as usize)
+            .map(event_line)
+            .collect()
+    };
+    let paragraph = Paragraph::new(lines)
+        .block(Block::default().title("Events").borders(Borders::ALL));
+    f.render_widget(paragraph, chunks[2]);
+}
+
+// one line of the event list: time, target, the change and how long the previous state lasted
+fn event_line(event: &StatusEvent) -> Line<'_> {
+    let mut spans = vec![
+        Span::raw(format!("{} ", event.time.format("%Y-%m-%d %H:%M:%S"))),
+        Span::styled(format!("{}: ", event.target), Style::default().fg(Color::Cyan)),
+        Span::styled(event.from.to_string(), Style::default().fg(status_color(event.from))),
+        Span::raw(" → "),
+        Span::styled(event.to.to_string(), Style::default().fg(status_color(event.to)).add_modifier(Modifier::BOLD)),
+    ];
+    match event.from {
+        Status::Down => spans.push(Span::styled(
+            format!("  outage {}", format_duration(event.duration)),
+            Style::default().fg(Color::Red),
+        )),
+        Status::Unknown => {}
+        from => spans.push(Span::raw(format!("  after {} {}", format_duration(event.duration), from))),
+    }
+    if event.flapping {
+        spans.push(Span::styled("  flapping", Style::default().fg(Color::Magenta)));
+    }
+    Line::from(spans)
+}
@@ -1,19 +1,74 @@
-use ratatui::backend::Backend;
 use ratatui::{symbols, Frame};
 use ratatui::layout::{Constraint, Direction, Layout, Rect};
 use ratatui::prelude::{Color, Line, Span, Style};
 use ratatui::widgets::{Axis, Block, Chart, Dataset, Paragraph, Wrap};
-use crate::ip_data::IpData;
-use crate::ui::utils::{calculate_avg_rtt, calculate_jitter, draw_errors_section};
+use nping::ip_data::IpData;
+use nping::error_log::ErrorLog;
+use crate::app::{AppState, ViewRow};
+use nping::stats::{calculate_avg_rtt, calculate_jitter};
+use crate::ui::utils::{calculate_group_stats, display_ip, draw_errors_section, error_marker, group_header_line, health_color, selected_style};
+
+/// most charts shown side by side
+const CHARTS_PER_ROW: usize = 5;
+
+// the targets of each row of charts. the rows of a hostname, like its ipv4 and ipv6 row in
+// dual-stack mode, are kept in one row unless there are more of them than fit
+fn chart_rows(ip_data: &[IpData], targets: &[usize]) -> Vec<Vec<usize>> {
+    let mut rows = Vec::new();
+    let mut row: Vec<usize> = Vec::new();
+    for host in targets.chunk_by(|&a, &b| ip_data[a].addr == ip_data[b].addr) {
+        if !row.is_empty() && row.len() + host.len() > CHARTS_PER_ROW {
+            rows.push(std::mem::take(&mut row));
+        }
+        for &index in host {
+            if row.len() == CHARTS_PER_ROW {
+                rows.push(std::mem::take(&mut row));
+            }
+            row.push(index);
+        }
+    }
+    if !row.is_empty() {
+        rows.push(row);
+    }
+    rows
+}
 
-pub fn draw_graph_view<B: Backend>(
+pub fn draw_graph_view(
     f: &mut Frame,
     ip_data: &[IpData],
-    errs: &[String]) {
+    errs: &ErrorLog,
+    state: &AppState) {
     let size = f.area();
-    let rows = (ip_data.len() as f64 / 5.0).ceil() as usize;
+    let view_rows = state.view_rows(ip_data);
+
+    // the charts of the visible targets, below a line per group
+    let mut targets = Vec::new();
+    let mut group_headers = Vec::new();
+    for row in &view_rows {
+        match row {
+            ViewRow::Target(index) => targets.push(*index),
+            ViewRow::Group { name, members, collapsed } => {
+                let all_members: Vec<&IpData> = ip_data.iter().filter(|data| data.group == *name).collect();
+                let selected = *collapsed && state.selected.is_some_and(|selected| members.first() == Some(&selected));
+                group_headers.push(group_header_line(name.as_deref(), &calculate_group_stats(&all_members), &state.thresholds, *collapsed, selected));
+            }
+        }
+    }
+
+    let chart_rows = chart_rows(ip_data, &targets);
+    // further addresses of the same hostname are dimmed so the group stands out, like in the point view
+    let continues_host: Vec<usize> = targets
+        .windows(2)
+        .filter(|pair| ip_data[pair[0]].addr == ip_data[pair[1]].addr)
+        .map(|pair| pair[1])
+        .collect();
+    let rows = chart_rows.len();
     let mut chunks = Vec::new();
 
+    if !group_headers.is_empty() {
+        chunks.push(Constraint::Length(group_headers.len() as u16));
+    }
+
     // compute the constraints
     for _ in 0..rows {
         chunks.push(Constraint::Percentage(100 / rows as u16));
@@ -28,20 +83,22 @@ pub fn draw_graph_view<B: Backend>(
         .constraints(chunks)
         .split(size);
 
-    for (row, vertical_chunk) in vertical_chunks.iter().enumerate().take(rows) {
-        let start = row * 5;
-        let end = (start + 5).min(ip_data.len());
-        let row_data = &ip_data[start..end];
+    let chart_chunks = if group_headers.is_empty() {
+        &vertical_chunks[..]
+    } else {
+        f.render_widget(Paragraph::new(group_headers), vertical_chunks[0]);
+        &vertical_chunks[1..]
+    };
+
+    for (row_targets, vertical_chunk) in chart_rows.iter().zip(chart_chunks) {
+        let row_data: Vec<&IpData> = row_targets.iter().map(|&index| &ip_data[index]).collect();
 
-        let horizontal_constraints: Vec<Constraint> = if row_data.len() == 5 {
-            row_data.iter().map(|_| Constraint::Percentage(20)).collect()
+        let width = 100 / CHARTS_PER_ROW as u16;
+        let horizontal_constraints: Vec<Constraint> = if rows > 1 {
+            // every chart is as wide as in a full row, a row with fewer charts is not stretched
+            row_data.iter().map(|_| Constraint::Percentage(width)).collect()
         } else {
-            // when the number of targets is less than 5, we need to adjust the size of each target
-            let mut size = 100;
-            if ip_data.len() > 5 {
-                size = row_data.len() * 20;
-            }
-            row_data.iter().map(|_| Constraint::Percentage(size as u16 / row_data.len() as u16)).collect()
+            row_data.iter().map(|_| Constraint::Percentage(100 / row_data.len() as u16)).collect()
         };
 
         let horizontal_chunks = Layout::default()
@@ -51,6 +108,10 @@ pub fn draw_graph_view<B: Backend>(
             .split(*vertical_chunk);
 
         for (i, data) in row_data.iter().enumerate() {
+            let is_selected = state.selected == Some(row_targets[i]);
+            let target_color = if continues_host.contains(&row_targets[i]) { Color::DarkGray } else { Color::Green };
+            // the charts of a hostname with several rows tell their addresses apart
+            let shares_host = targets.iter().filter(|&&index| ip_data[index].addr == data.addr).count() > 1;
             // compute the loss package rate for each target
             let loss_pkg = if data.timeout > 0 {
                 (data.timeout as f64 / (data.received as f64 + data.timeout as f64)) * 100.0
@@ -58,16 +119,14 @@ pub fn draw_graph_view<B: Backend>(
                 0.0
             };
 
-            let loss_pkg_color = if loss_pkg > 50.0 {
-                Color::Red
-            } else if loss_pkg > 0.0 {
-                Color::Yellow
-            } else {
-                Color::Green
-            };
+            let thresholds = state.thresholds.for_target(&data.options);
+            let loss_pkg_color = health_color(thresholds.loss(loss_pkg));
+            let avg_rtt_color = health_color(thresholds.avg_rtt(data));
 
             // render the content of each target
             let render_content = |f: &mut Frame, area: Rect| {
+                // short cells keep the title and chart, the recent records only fit below a usable chart
+                let records_height = if area.height >= 17 { 6 } else { 0 };
                 let inner_chunks = Layout::default()
                     .direction(Direction::Vertical)
                     .margin(1)
@@ -76,8 +135,8 @@ pub fn draw_graph_view<B: Backend>(
                             Constraint::Length(1),  // 目标标题
                             Constraint::Max(4),  // 段落占用较少行
                             Constraint::Max(20),     // chart 动态扩大
-                            Constraint::Length(1),
-                            Constraint::Length(6),
+                            Constraint::Length(records_height.min(1)),
+                            Constraint::Length(records_height),
                         ]
                             .as_ref(),
                     )
@@ -92,10 +151,18 @@ pub fn draw_graph_view<B: Backend>(
 
 
                 // render the target text
-                let target_text = Line::from(vec![
+                let mut target_text = Line::from(vec![
                     Span::styled("Target: ", Style::default()),
-                    Span::styled(&data.addr, Style::default().fg(Color::Green)),
+                    Span::styled(data.name(), selected_style(Style::default().fg(target_color), is_selected)),
                 ]);
+                if data.ip.is_empty() {
+                    target_text.push_span(Span::styled(format!(" ({})", display_ip(data)), Style::default().fg(Color::Red)));
+                } else if shares_host {
+                    target_text.push_span(Span::styled(format!(" ({})", data.ip), Style::default().fg(Color::DarkGray)));
+                }
+                if let Some(marker) = error_marker(errs, data.id) {
+                    target_text.push_span(marker);
+                }
 
                 let base_metric_text = Line::from(vec![
                     Span::styled("Last: ", Style::default()),
@@ -111,7 +178,7 @@ pub fn draw_graph_view<B: Backend>(
                     ),
                     Span::raw("  "),
                     Span::styled("Avg Rtt : ", Style::default()),
-                    Span::styled(format!("{:.2} ms", avg_rtt), Style::default().fg(Color::Green)),
+                    Span::styled(format!("{:.2} ms", avg_rtt), Style::default().fg(avg_rtt_color)),
                     Span::raw("  "),
                     Span::styled("Jitter: ", Style::default()),
                     Span::styled(format!("{:.2} ms", jitter), Style::default().fg(Color::Green)),
@@ -141,19 +208,47 @@ pub fn draw_graph_view<B: Backend>(
                     .map(|(i, &y)| (data.pop_count as f64 + i as f64 + 1.0, y))
                     .collect::<Vec<(f64, f64)>>();
 
-                let datasets = vec![Dataset::default()
+                let y_bounds = [0.0, data.max_rtt * 1.2];
+
+                // vertical markers where the target was re-resolved to a new address
+                let first_seq = data.pop_count + 1;
+                let last_seq = data.pop_count + data.rtts.len();
+                let dns_change_lines = data
+                    .dns_changes
+                    .iter()
+                    .filter(|change| change.seq >= first_seq && change.seq < last_seq)
+                    .map(|change| {
+                        let x = change.seq as f64 + 0.5;
+                        vec![(x, y_bounds[0]), (x, y_bounds[1])]
+                    })
+                    .collect::<Vec<Vec<(f64, f64)>>>();
+
+                let mut datasets = vec![Dataset::default()
                     .marker(symbols::Marker::HalfBlock)
                     .style(Style::default().fg(Color::Red))
                     .graph_type(ratatui::widgets::GraphType::Line)
                     .data(&data_points)];
-
-                let y_bounds = [0.0, data.max_rtt * 1.2];
+                for line in &dns_change_lines {
+                    datasets.push(Dataset::default()
+                        .marker(symbols::Marker::Braille)
+                        .style(Style::default().fg(Color::Yellow))
+                        .graph_type(ratatui::widgets::GraphType::Line)
+                        .data(line));
+                }
 
                 let x_range = data
                     .rtts
                     .iter()
                     .enumerate()
-                    .map(|(i, _)| Span::styled(format!("{}", i + 1 + data.pop_count), Style::default()))
+                    .map(|(i, _)| {
+                        let seq = i + 1 + data.pop_count;
+                        // the first probe on a new address is marked on the axis
+                        if data.dns_changes.iter().any(|change| change.seq + 1 == seq) {
+                            Span::styled(format!("{}⇄", seq), Style::default().fg(Color::Yellow))
+                        } else {
+                            Span::styled(format!("{}", seq), Style::default())
+                        }
+                    })
                     .collect::<Vec<Span>>();
 
                 let chart = Chart::new(datasets)
@@ -171,7 +266,7 @@ pub fn draw_graph_view<B: Backend>(
                             .bounds(y_bounds)
                             .labels(
                                 (0..=5)
-                                    .map(|i| Span::raw(format!("{:.2}ms", i as f64 * (y_bounds[1] / 5.0) as f64)))
+                                    .map(|i| Span::raw(format!("{:.2}ms", i as f64 * (y_bounds[1] / 5.0))))
                                     .collect::<Vec<Span>>(),
                             ),
                     )
@@ -216,5 +311,5 @@ pub fn draw_graph_view<B: Backend>(
     }
 
     let errors_chunk = vertical_chunks.last().unwrap();
-    draw_errors_section::<B>(f, errs, *errors_chunk);
+    draw_errors_section(f, errs, *errors_chunk);
 }
\ No newline at end of file
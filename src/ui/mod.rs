@@ -2,7 +2,16 @@ mod graph;
 mod utils;
 mod table;
 mod point;
+mod detail;
+mod errors;
+mod events;
 
 pub use graph::draw_graph_view;
-pub use table::draw_table_view;
-pub use point::draw_point_view;  // Export the new function
\ No newline at end of file
+pub use table::{draw_table_view, sorted_indices, SortColumn};
+pub use point::draw_point_view;  // Export the new function
+pub use detail::draw_detail_view;
+pub use errors::draw_error_log_view;
+pub use events::draw_event_log_view;
+
+#[cfg(test)]
+mod tests;
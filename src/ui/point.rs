@@ -1,28 +1,23 @@
-use ratatui::backend::Backend;
 use ratatui::Frame;
 use ratatui::layout::{Constraint, Direction, Layout, Rect};
 use ratatui::prelude::{Color, Line, Span, Style};
 use ratatui::widgets::{Block, Paragraph, Wrap};
-use crate::ip_data::IpData;
-use crate::ui::utils::{calculate_avg_rtt, calculate_jitter, calculate_loss_pkg, draw_errors_section};
-
-pub fn get_loss_color_and_emoji(loss_rate: f64) -> Color {
-    if loss_rate > 50.0 {
-        Color::Red
-    } else if loss_rate > 0.0 {
-        Color::Yellow
-    } else {
-        Color::Green
-    }
-}
-
-pub fn draw_point_view<B: Backend>(
+use nping::ip_data::IpData;
+use nping::error_log::ErrorLog;
+use crate::app::AppState;
+use nping::stats::{calculate_avg_rtt, calculate_jitter, calculate_loss_pkg};
+use crate::ui::utils::{display_ip, draw_errors_section, error_marker, health_color, selected_style};
+use nping::health::Health;
+
+pub fn draw_point_view(
     f: &mut Frame,
     ip_data: &[IpData],
-    errs: &[String],
+    errs: &ErrorLog,
     area: Rect,
+    state: &AppState,
 ) {
-    let data = ip_data.to_vec();
+    let order = state.display_order(ip_data);
+    let data: Vec<&IpData> = order.iter().map(|&i| &ip_data[i]).collect();
 
     // Calculate how much vertical space each IP will take (increased to account for multiple lines if needed)
     let ip_height = 5; // Increased to accommodate potential multi-line display
@@ -44,7 +39,10 @@ pub fn draw_point_view<B: Backend>(
         Span::styled("•", Style::default().fg(Color::Green)),
         Span::raw(" Healthy, "),
         Span::styled("↑", Style::default().fg(Color::Yellow)),
-        Span::raw(" High Latency (over 80% of max), "),
+        Span::raw(match state.thresholds.warn_rtt {
+            Some(warn_rtt) => format!(" High Latency (over {}ms), ", warn_rtt),
+            None => " High Latency (over 80% of max), ".to_string(),
+        }),
         Span::styled("✗", Style::default().fg(Color::Red)),
         Span::raw(" Timeout)"),
     ]);
@@ -65,15 +63,21 @@ pub fn draw_point_view<B: Backend>(
         let avg_rtt = calculate_avg_rtt(&ip.rtts);
         let jitter = calculate_jitter(&ip.rtts);
         let loss_pkg = calculate_loss_pkg(ip.timeout, ip.received);
-        let loss_pkg_color = get_loss_color_and_emoji(loss_pkg);
+        let thresholds = state.thresholds.for_target(&ip.options);
+        let loss_pkg_color = health_color(thresholds.loss(loss_pkg));
+
 
+        // further addresses of the same hostname are dimmed so the group stands out
+        let target_color = if i > 0 && data[i - 1].addr == ip.addr { Color::DarkGray } else { Color::Green };
 
         // Create the info line (row 1) with all metrics from table view
         let info_line = Line::from(vec![
             Span::raw("Target: "),
-            Span::styled(format!("{} ", ip.addr), Style::default().fg(Color::Green)),
+            Span::styled(ip.name().to_string(), selected_style(Style::default().fg(target_color), state.selected == Some(order[i]))),
+            error_marker(errs, ip.id).unwrap_or_default(),
+            Span::raw(" "),
             Span::raw("Ip: "),
-            Span::styled(format!("{} ", ip.ip), Style::default().fg(Color::Green)),
+            Span::styled(format!("{} ", display_ip(ip)), Style::default().fg(if ip.unresolved { Color::Red } else { Color::Green })),
             Span::raw("Last: "),
             Span::styled(
                 if ip.last_attr == 0.0 {
@@ -86,7 +90,7 @@ pub fn draw_point_view<B: Backend>(
                 Style::default().fg(Color::Green)
             ),
             Span::raw(" Avg: "),
-            Span::styled(format!("{:.2}ms", avg_rtt), Style::default().fg(Color::Green)),
+            Span::styled(format!("{:.2}ms", avg_rtt), Style::default().fg(health_color(thresholds.avg_rtt(ip)))),
             Span::raw(" Max: "),
             Span::styled(format!("{:.2}ms", ip.max_rtt), Style::default().fg(Color::Green)),
             Span::raw(" Min: "),
@@ -98,15 +102,16 @@ pub fn draw_point_view<B: Backend>(
 
         let mut points_spans = Vec::new();
         for &rtt in &ip.rtts {
+            let color = health_color(thresholds.rtt(rtt, ip.max_rtt));
             if rtt < 0.0 {
                 // Timeout/packet loss - red X
-                points_spans.push(Span::styled("✗", Style::default().fg(Color::Red)));
-            } else if rtt > ip.max_rtt * 0.8 {
-                // High latency () - yellow dot
-                points_spans.push(Span::styled("↑", Style::default().fg(Color::Yellow)));
+                points_spans.push(Span::styled("✗", Style::default().fg(color)));
+            } else if thresholds.rtt(rtt, ip.max_rtt) != Health::Ok {
+                // High latency - yellow, or red above the critical rtt
+                points_spans.push(Span::styled("↑", Style::default().fg(color)));
             } else {
                 // Normal latency - green dot
-                points_spans.push(Span::styled("•", Style::default().fg(Color::Green)));
+                points_spans.push(Span::styled("•", Style::default().fg(color)));
             }
             points_spans.push(Span::raw(" ")); // Space between points
         }
@@ -116,7 +121,7 @@ pub fn draw_point_view<B: Backend>(
             .direction(Direction::Vertical)
             .constraints([
                 Constraint::Length(1),  // Info line
-                Constraint::Min(4), // Points area with enough space for multiple lines
+                Constraint::Min(1), // Points area, takes the rest so the info line is never squeezed out
             ].as_ref())
             .split(ip_chunks[i]);
 
@@ -134,6 +139,6 @@ pub fn draw_point_view<B: Backend>(
     }
 
     // Draw errors section at the bottom
-    let errors_chunk = chunks[1];
-    draw_errors_section::<B>(f, errs, errors_chunk);
+    let errors_chunk = chunks[2];
+    draw_errors_section(f, errs, errors_chunk);
 }
\ No newline at end of file
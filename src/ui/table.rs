@@ -1,36 +1,146 @@
-use ratatui::backend::Backend;
 use ratatui::Frame;
 use ratatui::layout::{Constraint, Direction, Layout, Rect};
 use ratatui::prelude::{Color, Modifier, Style};
-use ratatui::widgets::{Block, Paragraph, Row, Table};
-use crate::ip_data::IpData;
-use crate::ui::utils::{calculate_avg_rtt, calculate_jitter, calculate_loss_pkg, draw_errors_section};
+use ratatui::widgets::{Block, Paragraph, Row, Table, TableState};
+use nping::ip_data::IpData;
+use nping::error_log::ErrorLog;
+use crate::app::{AppState, ViewRow};
+use nping::health::Health;
+use std::cmp::Ordering;
+use nping::stats::{calculate_avg_rtt, calculate_jitter, calculate_loss_pkg};
+use crate::ui::utils::{calculate_group_stats, display_ip, draw_errors_section, error_marker};
 
+/// columns the table view can be sorted by
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SortColumn {
+    Target,
+    Ip,
+    Last,
+    Avg,
+    Max,
+    Min,
+    Jitter,
+    #[default]
+    Loss,
+    P95,
+    P99,
+}
 
-pub fn draw_table_view<B: Backend>(
-    f: &mut Frame,
-    ip_data: &[IpData],
-    errs: &[String],
-    area: Rect,
-) {
-    let mut data = ip_data.to_vec();
+impl SortColumn {
+    /// all sortable columns in the order they are displayed
+    pub const ALL: [SortColumn; 10] = [
+        SortColumn::Target,
+        SortColumn::Ip,
+        SortColumn::Last,
+        SortColumn::Avg,
+        SortColumn::Max,
+        SortColumn::Min,
+        SortColumn::Jitter,
+        SortColumn::Loss,
+        SortColumn::P95,
+        SortColumn::P99,
+    ];
 
-    data.sort_by(|a, b| {
-        let loss_a = calculate_loss_pkg(a.timeout, a.received);
-        let loss_b = calculate_loss_pkg(b.timeout, b.received);
+    pub fn title(&self) -> &'static str {
+        match self {
+            SortColumn::Target => "Target",
+            SortColumn::Ip => "Ip",
+            SortColumn::Last => "Last Rtt",
+            SortColumn::Avg => "Avg Rtt",
+            SortColumn::Max => "Max",
+            SortColumn::Min => "Min",
+            SortColumn::Jitter => "Jitter",
+            SortColumn::Loss => "Loss",
+            SortColumn::P95 => "P95",
+            SortColumn::P99 => "P99",
+        }
+    }
+
+    /// the column after this one, wrapping around at the end
+    pub fn next(&self) -> SortColumn {
+        let pos = SortColumn::ALL.iter().position(|c| c == self).unwrap_or(0);
+        SortColumn::ALL[(pos + 1) % SortColumn::ALL.len()]
+    }
+}
+
+// numeric sort key of a column, timeouts are treated as the worst value
+fn sort_value(data: &IpData, column: SortColumn) -> f64 {
+    match column {
+        SortColumn::Last => {
+            if data.last_attr < 0.0 { f64::INFINITY } else { data.last_attr }
+        }
+        SortColumn::Avg => calculate_avg_rtt(&data.rtts),
+        SortColumn::Max => data.max_rtt,
+        SortColumn::Min => data.min_rtt,
+        SortColumn::Jitter => calculate_jitter(&data.rtts),
+        SortColumn::Loss => calculate_loss_pkg(data.timeout, data.received),
+        SortColumn::P95 => data.history_percentile(95.0),
+        SortColumn::P99 => data.history_percentile(99.0),
+        SortColumn::Target | SortColumn::Ip => 0.0,
+    }
+}
 
+fn compare_ip_data(a: &IpData, b: &IpData, column: SortColumn) -> Ordering {
+    match column {
+        SortColumn::Target => a.name().cmp(b.name()),
+        SortColumn::Ip => match (a.ip.parse::<std::net::IpAddr>(), b.ip.parse::<std::net::IpAddr>()) {
+            (Ok(ip_a), Ok(ip_b)) => ip_a.cmp(&ip_b),
+            _ => a.ip.cmp(&b.ip),
+        },
         // sort by loss rate first, then by latency
-        match loss_a.partial_cmp(&loss_b) {
-            Some(std::cmp::Ordering::Equal) => {
+        SortColumn::Loss => sort_value(a, column)
+            .partial_cmp(&sort_value(b, column))
+            .unwrap_or(Ordering::Equal)
+            .then_with(|| {
                 let avg_a = calculate_avg_rtt(&a.rtts);
                 let avg_b = calculate_avg_rtt(&b.rtts);
-                avg_a.partial_cmp(&avg_b).unwrap_or(std::cmp::Ordering::Equal)
-            }
-            Some(ordering) => ordering,
-            None => std::cmp::Ordering::Equal
-        }
+                avg_a.partial_cmp(&avg_b).unwrap_or(Ordering::Equal)
+            }),
+        _ => sort_value(a, column)
+            .partial_cmp(&sort_value(b, column))
+            .unwrap_or(Ordering::Equal),
+    }
+}
+
+/// indices of the targets sorted by the given column, ties keep their original order.
+/// targets without an address yet have no statistics and are always listed last
+pub fn sorted_indices(data: &[IpData], column: SortColumn, desc: bool) -> Vec<usize> {
+    let mut indices: Vec<usize> = (0..data.len()).collect();
+    indices.sort_by(|&a, &b| {
+        let ordering = compare_ip_data(&data[a], &data[b], column);
+        let ordering = if desc { ordering.reverse() } else { ordering };
+        data[a].ip.is_empty().cmp(&data[b].ip.is_empty()).then(ordering)
     });
 
+    // rows of the same hostname stay together, placed where the best of them ranks
+    let mut grouped = Vec::with_capacity(indices.len());
+    for &index in &indices {
+        if grouped.iter().any(|&i: &usize| data[i].addr == data[index].addr) {
+            continue;
+        }
+        grouped.extend(indices.iter().filter(|&&i| data[i].addr == data[index].addr));
+    }
+    grouped
+}
+
+
+pub fn draw_table_view(
+    f: &mut Frame,
+    ip_data: &[IpData],
+    errs: &ErrorLog,
+    area: Rect,
+    state: &AppState,
+) {
+    let sort_column = state.sort_column;
+    let sort_desc = state.sort_desc;
+    let view_rows = state.view_rows(ip_data);
+    let data: Vec<&IpData> = view_rows
+        .iter()
+        .filter_map(|row| match row {
+            ViewRow::Target(index) => Some(&ip_data[*index]),
+            ViewRow::Group { .. } => None,
+        })
+        .collect();
 
     let header_style = Style::default()
         .add_modifier(Modifier::BOLD);
@@ -38,40 +148,56 @@ pub fn draw_table_view<B: Backend>(
     let selected_style = Style::default()
         .add_modifier(Modifier::REVERSED);
 
-    // create header
-    let header = Row::new(vec![
-        "Rank",
-        "Target",
-        "Ip",
-        "Last Rtt",
-        "Avg Rtt",
-        "Max",
-        "Min",
-        "Jitter",
-        "Loss",
-    ])
+    let sort_arrow = if sort_desc { "↓" } else { "↑" };
+
+    // create header, the sorted column is marked with an arrow
+    let header = Row::new(
+        std::iter::once("Rank".to_string()).chain(SortColumn::ALL.iter().map(|column| {
+            if *column == sort_column {
+                format!("{} {}", column.title(), sort_arrow)
+            } else {
+                column.title().to_string()
+            }
+        })),
+    )
         .style(header_style)
         .height(1);
 
 
+    // further rows of a hostname only point at the first one
+    let continues_group: Vec<bool> = (0..data.len())
+        .map(|index| index > 0 && data[index - 1].addr == data[index].addr && data[index - 1].group == data[index].group)
+        .collect();
+
+    let target_count = data.len();
+
     // create rows
-    let rows = data.iter().enumerate().map(|(index, data)| {
+    let target_rows = data.iter().enumerate().map(|(index, data)| {
         let avg_rtt = calculate_avg_rtt(&data.rtts);
         let jitter = calculate_jitter(&data.rtts);
         let loss_pkg = calculate_loss_pkg(data.timeout, data.received);
+        // a percentile of the few rtts of the table would just be their maximum
+        let p95 = data.history_percentile(95.0);
+        let p99 = data.history_percentile(99.0);
 
         let rank = match index {
             0 => "🥇".to_string(),
             1 => "🥈".to_string(),
             2 => "🥉".to_string(),
-            n if n < 10 && n != ip_data.len() - 1 => "🏆".to_string(),
+            n if n < 10 && n != target_count - 1 => "🏆".to_string(),
             _ => "🐢".to_string(),
         };
 
         let row = Row::new(vec![
             rank,
-            data.addr.clone(),
-            data.ip.clone(),
+            {
+                let name = if continues_group[index] { "  └".to_string() } else { data.name().to_string() };
+                match error_marker(errs, data.id) {
+                    Some(marker) => format!("{}{}", name, marker.content),
+                    None => name,
+                }
+            },
+            display_ip(data),
             if data.last_attr == 0.0 {
                 "< 0.01ms".to_string()
             } else if data.last_attr == -1.0 {
@@ -84,17 +210,53 @@ pub fn draw_table_view<B: Backend>(
             format!("{:.2}ms", data.min_rtt),
             format!("{:.2}ms", jitter),
             format!("{:.2}%", loss_pkg),
+            format!("{:.2}ms", p95),
+            format!("{:.2}ms", p99),
         ]).height(1);
 
         // highlight the row with different colors
-        if loss_pkg > 50.0 {
-            row.style(Style::default().bg(Color::Red).fg(Color::White)) // 淡红色
-        } else if loss_pkg > 0.0 {
-            row.style(Style::default().bg(Color::Yellow).fg(Color::White)) // 淡黄色
-        } else {
-            row
+        match state.thresholds.for_target(&data.options).target(data) {
+            Health::Crit => row.style(Style::default().bg(Color::Red).fg(Color::White)), // 淡红色
+            Health::Warn => row.style(Style::default().bg(Color::Yellow).fg(Color::White)), // 淡黄色
+            Health::Ok => row,
         }
     });
+    let mut target_rows = target_rows.collect::<Vec<Row>>().into_iter();
+
+    // group headers show the aggregated statistics of all their members in the matching columns
+    let group_style = Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD);
+    let rows: Vec<Row> = view_rows
+        .iter()
+        .filter_map(|row| match row {
+            ViewRow::Target(_) => target_rows.next(),
+            ViewRow::Group { name, collapsed, .. } => {
+                let members: Vec<&IpData> = ip_data.iter().filter(|data| data.group == *name).collect();
+                let stats = calculate_group_stats(&members);
+                Some(Row::new(vec![
+                    if *collapsed { "▸" } else { "▾" }.to_string(),
+                    format!("{} ({})", name.as_deref().unwrap_or("ungrouped"), stats.members),
+                    if stats.down > 0 { format!("{} down", stats.down) } else { String::new() },
+                    String::new(),
+                    format!("{:.2}ms", stats.avg_rtt),
+                    format!("{:.2}ms", stats.max_rtt),
+                    String::new(),
+                    String::new(),
+                    format!("{:.2}%", stats.loss),
+                    String::new(),
+                    String::new(),
+                ]).style(group_style))
+            }
+        })
+        .collect();
+
+    // the selected target, or the header of its collapsed group
+    let selected_row = state.selected.and_then(|selected| {
+        view_rows.iter().position(|row| match row {
+            ViewRow::Target(index) => *index == selected,
+            ViewRow::Group { members, collapsed: true, .. } => members.first() == Some(&selected),
+            ViewRow::Group { .. } => false,
+        })
+    });
 
 
     let table = Table::new(
@@ -103,17 +265,27 @@ pub fn draw_table_view<B: Backend>(
             Constraint::Percentage(3),
             Constraint::Percentage(15),
             Constraint::Percentage(15),
-            Constraint::Percentage(10),
-            Constraint::Percentage(10),
-            Constraint::Percentage(10),
-            Constraint::Percentage(10),
-            Constraint::Percentage(10),
-            Constraint::Percentage(10),
+            Constraint::Percentage(8),
+            Constraint::Percentage(8),
+            Constraint::Percentage(8),
+            Constraint::Percentage(8),
+            Constraint::Percentage(8),
+            Constraint::Percentage(8),
+            Constraint::Percentage(8),
+            Constraint::Percentage(8),
         ],
     )
         .header(header)
         .block(Block::default()
-            .title("🏎  Nping Table (Sort by: Loss Rate ↑ then Latency ↑)"))
+            .title(format!(
+                "🏎  Nping Table (Sort by: {} {}, press s to change column, S to reverse){}",
+                sort_column.title(),
+                sort_arrow,
+                match &state.group_filter {
+                    Some(group) => format!(" [group: {}, press g for the next]", group),
+                    None => String::new(),
+                }
+            )))
         .row_highlight_style(selected_style)
         .highlight_symbol(">> ");
 
@@ -129,8 +301,10 @@ pub fn draw_table_view<B: Backend>(
     // black line
     let blank = Paragraph::new("");
     f.render_widget(blank, chunks[0]);
-    f.render_widget(table, chunks[1]);
+    let mut table_state = TableState::default()
+        .with_selected(selected_row);
+    f.render_stateful_widget(table, chunks[1], &mut table_state);
 
     let errors_chunk = chunks.last().unwrap();
-    draw_errors_section::<B>(f, errs, *errors_chunk);
+    draw_errors_section(f, errs, *errors_chunk);
 }
\ No newline at end of file
@@ -0,0 +1,365 @@
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use chrono::{DateTime, Local, TimeZone};
+use crate::app::AppState;
+use crate::draw::draw_interface;
+use nping::error_log::{ErrorKind, ErrorLog};
+use nping::health::Thresholds;
+use nping::ip_data::{apply_event, IpData};
+use nping::network::{IpFamily, PingEvent};
+use crate::replay::ReplayProgress;
+use nping::status::EventLog;
+use ratatui::backend::TestBackend;
+use ratatui::buffer::Buffer;
+use ratatui::style::Color;
+use ratatui::text::Span;
+use ratatui::Terminal;
+
+const VIEWS: [&str; 3] = ["graph", "table", "point"];
+
+fn start() -> DateTime<Local> {
+    Local.with_ymd_and_hms(2024, 1, 1, 12, 0, 0).unwrap()
+}
+
+// targets with ten replies each, the rtts rise with the index of the target and of the probe
+fn targets(n: usize) -> Vec<IpData> {
+    (0..n)
+        .map(|i| {
+            let mut data = IpData::new(i + 1, format!("host-{}", i + 1), format!("10.0.0.{}", i + 1));
+            for probe in 0..10 {
+                data.update_rtt(10.0 + (i * 3 + probe) as f64, 10, start() + chrono::Duration::seconds(probe as i64));
+            }
+            data
+        })
+        .collect()
+}
+
+// targets that never replied
+fn timed_out(n: usize) -> Vec<IpData> {
+    (0..n)
+        .map(|i| {
+            let mut data = IpData::new(i + 1, format!("host-{}", i + 1), format!("10.0.0.{}", i + 1));
+            for probe in 0..10 {
+                data.update_timeout(10, start() + chrono::Duration::seconds(probe as i64));
+            }
+            data
+        })
+        .collect()
+}
+
+fn state(view: &str) -> AppState {
+    AppState::new(view.to_string(), Arc::new(Mutex::new(false)), Thresholds::default())
+}
+
+// the screen as lines of text, wide characters take up one entry
+fn render(ip_data: &[IpData], errs: &ErrorLog, events: &EventLog, state: &AppState, width: u16, height: u16) -> Vec<String> {
+    let buffer = render_buffer(ip_data, errs, events, state, width, height);
+    (0..height)
+        .map(|y| {
+            let mut line = String::new();
+            let mut skip = 0;
+            for x in 0..width {
+                let symbol = buffer[(x, y)].symbol();
+                if skip == 0 {
+                    line.push_str(symbol);
+                }
+                skip = skip.max(Span::raw(symbol).width()).saturating_sub(1);
+            }
+            line.trim_end().to_string()
+        })
+        .collect()
+}
+
+fn render_buffer(ip_data: &[IpData], errs: &ErrorLog, events: &EventLog, state: &AppState, width: u16, height: u16) -> Buffer {
+    let mut terminal = Terminal::new(TestBackend::new(width, height)).unwrap();
+    draw_interface(&mut terminal, ip_data, errs, events, state).unwrap();
+    terminal.backend().buffer().clone()
+}
+
+fn render_view(view: &str, ip_data: &[IpData], width: u16, height: u16) -> Vec<String> {
+    render_view_state(&state(view), ip_data, width, height)
+}
+
+fn render_view_state(state: &AppState, ip_data: &[IpData], width: u16, height: u16) -> Vec<String> {
+    render(ip_data, &ErrorLog::default(), &EventLog::default(), state, width, height)
+}
+
+// whether any line contains the text
+fn shows(lines: &[String], text: &str) -> bool {
+    lines.iter().any(|line| line.contains(text))
+}
+
+#[test]
+fn table_with_one_target() {
+    let lines = render_view("table", &targets(1), 100, 9);
+    assert_eq!(lines, [
+        "",
+        "🏎  Nping Table (Sort by: Loss ↑, press s to change column, S to reverse)",
+        "Ran Target       Ip          Last Rtt Avg Rtt  Max      Min      Jitter   Loss ↑   P95      P99",
+        "🥇  host-1       10.0.0.1    19.00ms  14.50ms  19.00ms  10.00ms  1.00ms   0.00%    19.00ms  19.00ms",
+        "",
+        "",
+        "",
+        "",
+        "",
+    ]);
+}
+
+#[test]
+fn table_percentiles_cover_the_session() {
+    // rtts of 1ms to 100ms, the table only keeps the last ten of them
+    let mut data = IpData::new(1, "host-1".to_string(), "10.0.0.1".to_string());
+    for probe in 0..100 {
+        data.update_rtt((probe + 1) as f64, 10, start() + chrono::Duration::seconds(probe));
+    }
+    let lines = render_view("table", &[data], 100, 9);
+    assert!(lines[3].ends_with("95.00ms  99.00ms"), "{}", lines[3]);
+}
+
+#[test]
+fn table_with_all_targets_timed_out() {
+    let lines = render_view("table", &timed_out(2), 100, 10);
+    assert_eq!(lines[2..5], [
+        "Ran Target       Ip          Last Rtt Avg Rtt  Max      Min      Jitter   Loss ↑   P95      P99",
+        "🥇  host-1       10.0.0.1    0.0ms    0.00ms   0.00ms   0.00ms   0.00ms   100.00%  0.00ms   0.00ms",
+        "🥈  host-2       10.0.0.2    0.0ms    0.00ms   0.00ms   0.00ms   0.00ms   100.00%  0.00ms   0.00ms",
+    ]);
+}
+
+#[test]
+fn table_with_many_targets() {
+    let lines = render_view("table", &targets(25), 100, 40);
+    for i in 1..=25 {
+        let row = format!("host-{:<7} 10.0.0.{}", i, i);
+        assert!(shows(&lines, &row), "missing row {}", row);
+    }
+}
+
+#[test]
+fn point_with_two_targets() {
+    let lines = render_view("point", &targets(2), 60, 14);
+    assert_eq!(lines[..8], [
+        " 🏎  Nping Point View (• Healthy, ↑ High Latency (over 80% of",
+        "",
+        " Target: host-1 Ip: 10.0.0.1 Last: 19.00ms Avg: 14.50ms",
+        " • • • • • • ↑ ↑ ↑ ↑",
+        "",
+        " Target: host-2 Ip: 10.0.0.2 Last: 22.00ms Avg: 17.50ms",
+        " • • • • • ↑ ↑ ↑ ↑ ↑",
+        "",
+    ]);
+}
+
+#[test]
+fn point_with_all_targets_timed_out() {
+    let lines = render_view("point", &timed_out(1), 60, 16);
+    assert_eq!(lines[2..4], [
+        " Target: host-1 Ip: 10.0.0.1 Last: 0.0ms Avg: 0.00ms Max:",
+        " ✗ ✗ ✗ ✗ ✗ ✗ ✗ ✗ ✗ ✗",
+    ]);
+}
+
+#[test]
+fn point_with_many_targets() {
+    let lines = render_view("point", &targets(25), 100, 140);
+    for i in 1..=25 {
+        assert!(shows(&lines, &format!("Target: host-{} Ip: 10.0.0.{} ", i, i)), "missing host-{}", i);
+    }
+}
+
+#[test]
+fn graph_with_one_target() {
+    let lines = render_view("graph", &targets(1), 80, 22);
+    assert_eq!(lines[3..6], [
+        "   Target: host-1",
+        "   Last: 19.0ms  Avg Rtt : 14.50 ms  Jitter: 1.00 ms  Max: 19.00 ms  Min:",
+        "   10.00 ms  Loss: 0.00%",
+    ]);
+    // the chart gets the room before the recent records
+    assert!(shows(&lines, "22.80ms│"));
+    assert!(!shows(&lines, "Recent Records:"));
+
+    let lines = render_view("graph", &targets(1), 80, 30);
+    assert!(shows(&lines, "22.80ms│"));
+    assert!(shows(&lines, "Recent Records:"));
+    assert!(shows(&lines, "   10.0.0.1 19ms"));
+}
+
+#[test]
+fn graph_with_all_targets_timed_out() {
+    let lines = render_view("graph", &timed_out(1), 80, 30);
+    assert!(shows(&lines, "Last: 0.0ms  Avg Rtt : 0.00 ms"));
+    assert!(shows(&lines, "Loss: 100.00%"));
+    assert!(shows(&lines, "   10.0.0.1 timeout"));
+}
+
+#[test]
+fn graph_with_many_targets() {
+    let lines = render_view("graph", &targets(25), 150, 60);
+    // five charts in a row, each with its title and chart
+    assert_eq!(lines.iter().filter(|line| line.contains("Target: host-")).count(), 5);
+    assert_eq!(lines.iter().filter(|line| line.contains("ms│")).count(), 5);
+    for i in 1..=25 {
+        assert!(shows(&lines, &format!("Target: host-{}", i)), "missing host-{}", i);
+    }
+}
+
+#[test]
+fn graph_keeps_the_rows_of_a_host_together() {
+    // an ip literal has a single row, each hostname a pair of ipv4 and ipv6 rows
+    let mut ip_data = Vec::new();
+    for (i, host) in ["10.0.0.1", "dual-a", "dual-a", "dual-b", "dual-b", "10.0.0.2", "dual-c", "dual-c"].iter().enumerate() {
+        let mut data = IpData::new(i + 1, host.to_string(), format!("10.0.1.{}", i + 1));
+        data.family = Some(if i > 0 && ip_data.last().is_some_and(|last: &IpData| last.addr == *host) { IpFamily::V6 } else { IpFamily::V4 });
+        data.update_rtt(10.0, 10, start());
+        ip_data.push(data);
+    }
+    let lines = render_view("graph", &ip_data, 200, 60);
+    let titles: Vec<&String> = lines.iter().filter(|line| line.contains("Target: ")).collect();
+    assert_eq!(titles.len(), 2);
+    // the first row is full with the literal and two pairs, no pair is split across rows
+    assert_eq!(titles[0].matches("Target: ").count(), 5);
+    assert_eq!(titles[0].matches("Target: dual-b").count(), 2);
+    assert_eq!(titles[1].matches("Target: ").count(), 3);
+    assert_eq!(titles[1].matches("Target: dual-c").count(), 2);
+}
+
+#[test]
+fn graph_tells_the_addresses_of_a_host_apart() {
+    let mut ip_data = Vec::new();
+    for (i, (host, ip)) in [("multi", "10.0.0.1"), ("multi", "10.0.0.2"), ("single", "10.0.0.3")].iter().enumerate() {
+        let mut data = IpData::new(i + 1, host.to_string(), ip.to_string());
+        data.update_rtt(10.0, 10, start());
+        ip_data.push(data);
+    }
+    let lines = render_view("graph", &ip_data, 150, 30);
+    assert!(shows(&lines, "Target: multi (10.0.0.1)"));
+    assert!(shows(&lines, "Target: multi (10.0.0.2)"));
+    assert!(shows(&lines, "Target: single"));
+    assert!(!shows(&lines, "single (10.0.0.3)"));
+}
+
+#[test]
+fn views_without_targets() {
+    let lines = render_view("table", &[], 80, 8);
+    assert!(shows(&lines, "Nping Table"));
+    assert!(lines[3..].iter().all(|line| line.is_empty()));
+
+    for view in ["graph", "point"] {
+        let lines = render_view(view, &[], 80, 24);
+        assert!(!shows(&lines, "Target"), "{} view shows a target", view);
+    }
+}
+
+#[test]
+fn tiny_terminals() {
+    let mut ip_data = targets(3);
+    let mut events = EventLog::default();
+    for probe in 0..5 {
+        apply_event(&mut ip_data, PingEvent::Timeout { id: 1 }, 10, &Thresholds::default(), &mut events, start() + chrono::Duration::seconds(20 + probe));
+    }
+    let mut errs = ErrorLog::default();
+    errs.push(Some(1), "host-1", ErrorKind::Ping, "network unreachable");
+
+    let mut screens: Vec<AppState> = Vec::new();
+    for view in VIEWS {
+        screens.push(state(view));
+        let mut detail = state(view);
+        detail.selected = Some(0);
+        detail.show_detail = true;
+        screens.push(detail);
+        let mut overlays = state(view);
+        *overlays.paused.lock().unwrap() = true;
+        overlays.input = Some("example.com".to_string());
+        screens.push(overlays);
+    }
+    let mut log = state("graph");
+    log.show_errors = true;
+    screens.push(log);
+    let mut log = state("graph");
+    log.show_events = true;
+    screens.push(log);
+
+    for state in &screens {
+        for (width, height) in [(0, 0), (1, 1), (10, 3), (20, 5), (200, 3), (3, 60)] {
+            for data in [&ip_data[..], &[]] {
+                let lines = render(data, &errs, &events, state, width, height);
+                assert_eq!(lines.len(), height as usize);
+            }
+        }
+    }
+}
+
+#[test]
+fn detail_view() {
+    let mut ip_data = targets(2);
+    let mut events = EventLog::default();
+    for probe in 0..5 {
+        apply_event(&mut ip_data, PingEvent::Timeout { id: 1 }, 10, &Thresholds::default(), &mut events, start() + chrono::Duration::seconds(20 + probe));
+    }
+    let mut errs = ErrorLog::default();
+    errs.push(Some(1), "host-1", ErrorKind::Ping, "network unreachable");
+    let mut state = state("table");
+    state.selected = Some(0);
+    state.show_detail = true;
+    // the last ten probes average 17ms, the whole session 14.5ms
+    state.thresholds.warn_rtt = Some(16.0);
+
+    let lines = render(&ip_data, &errs, &events, &state, 90, 30);
+    assert_eq!(lines[0], " 🏎  Nping Detail host-1 (10.0.0.1)  press Enter or Esc to close");
+    assert!(shows(&lines, "│Sent: 15  Received: 10  Timeout: 5  Loss:"));
+    // the whole session, not only the last ten probes
+    assert!(shows(&lines, "│Last: timeout  Avg: 14.50ms  Jitter: 2.07ms"));
+    // the average is coloured by its own value
+    let buffer = render_buffer(&ip_data, &errs, &events, &state, 90, 30);
+    let y = lines.iter().position(|line| line.contains("Avg: 14.50ms")).unwrap();
+    let x = lines[y][..lines[y].find("14.50ms").unwrap()].chars().count();
+    assert_eq!(buffer[(x as u16, y as u16)].fg, Color::Green);
+    assert!(shows(&lines, "│Min: 10.00ms  Max: 19.00ms"));
+    assert!(shows(&lines, "│P50: 14.00ms  P95: 19.00ms  P99: 19.00ms"));
+    assert!(shows(&lines, "│12:00:24.000 #15 10.0.0.1 timeout"));
+    assert!(shows(&lines, "[ping] host-1: network unreachable"));
+}
+
+#[test]
+fn event_and_error_logs() {
+    let mut ip_data = targets(2);
+    let mut events = EventLog::default();
+    for probe in 0..5 {
+        apply_event(&mut ip_data, PingEvent::Timeout { id: 1 }, 10, &Thresholds::default(), &mut events, start() + chrono::Duration::seconds(20 + probe));
+    }
+    let mut errs = ErrorLog::default();
+    errs.push(Some(1), "host-1", ErrorKind::Ping, "network unreachable");
+
+    // a replay pins the current time, so the durations do not depend on the clock
+    let mut state = state("table");
+    state.replay = Some(ReplayProgress {
+        started: start(),
+        position: Duration::from_secs(30),
+        duration: Duration::from_secs(60),
+        speed: 1.0,
+        playing: false,
+    });
+    state.show_events = true;
+    let lines = render(&ip_data, &errs, &events, &state, 90, 12);
+    assert_eq!(lines[2], "│host-1  down     for 8s         outages: 1    downtime: 8s                              │");
+    assert_eq!(lines[3], "│host-2  unknown  for 0s         outages: 0    downtime: 0s                              │");
+    assert!(shows(&lines, "│2024-01-01 12:00:22 host-1: unknown → down"));
+    assert!(lines[11].starts_with(" ⏸ Replay 2024-01-01 12:00:30 30s / 1m 0s  1x"));
+
+    state.show_events = false;
+    state.show_errors = true;
+    let lines = render(&ip_data, &errs, &events, &state, 90, 12);
+    assert!(lines[0].starts_with(" 🏎  Nping Error Log 1 errors"));
+    assert!(shows(&lines, "[ping] host-1: network unreachable"));
+}
+
+#[test]
+fn paused_and_input_overlays() {
+    let mut state = state("table");
+    *state.paused.lock().unwrap() = true;
+    state.input = Some("example.com".to_string());
+    let lines = render_view_state(&state, &targets(1), 100, 9);
+    assert!(lines[0].ends_with("⏸ PAUSED (p to resume)"));
+    assert!(lines[8].starts_with(" Add target:  example.com█  (Enter to add, Esc to cancel)"));
+}
@@ -1,57 +1,134 @@
 use std::collections::VecDeque;
-use ratatui::backend::Backend;
 use ratatui::Frame;
 use ratatui::layout::Rect;
-use ratatui::prelude::{Color, Line, Span, Style};
+use ratatui::prelude::{Color, Line, Modifier, Span, Style};
 use ratatui::widgets::{Block, Borders, Paragraph, Wrap};
+use nping::error_log::{ErrorLog, ErrorRecord};
+use nping::health::{Health, Thresholds};
+use nping::ip_data::IpData;
+use nping::stats::{calculate_avg_rtt, calculate_loss_pkg};
+use nping::status::Status;
 
-pub fn calculate_avg_rtt(rtt: &VecDeque<f64>) -> f64 {
-    if !rtt.is_empty() {
-        let valid_rtt: Vec<f64> = rtt.iter().cloned().filter(|&rtt| rtt >= 0.0).collect();
-        if !valid_rtt.is_empty() {
-            let sum: f64 = valid_rtt.iter().sum();
-            sum / valid_rtt.len() as f64
-        } else {
-            0.0
-        }
-    } else {
-        0.0
+/// statistics of a group over all its members
+#[derive(Debug, Clone, Default)]
+pub struct GroupStats {
+    pub(crate) members: usize,
+    /// average of the valid rtts of all members
+    pub(crate) avg_rtt: f64,
+    pub(crate) max_rtt: f64,
+    /// loss over all probes of all members
+    pub(crate) loss: f64,
+    /// members that are down
+    pub(crate) down: usize,
+}
+
+pub fn calculate_group_stats(members: &[&IpData]) -> GroupStats {
+    let rtts: VecDeque<f64> = members.iter().flat_map(|data| data.rtts.iter().copied()).collect();
+    GroupStats {
+        members: members.len(),
+        avg_rtt: calculate_avg_rtt(&rtts),
+        max_rtt: members.iter().map(|data| data.max_rtt).fold(0.0, f64::max),
+        loss: calculate_loss_pkg(
+            members.iter().map(|data| data.timeout).sum(),
+            members.iter().map(|data| data.received).sum(),
+        ),
+        down: members.iter().filter(|data| data.status.status == Status::Down).count(),
+    }
+}
+
+// the header line of a group with its aggregated statistics
+pub fn group_header_line(name: Option<&str>, stats: &GroupStats, thresholds: &Thresholds, collapsed: bool, selected: bool) -> Line<'static> {
+    let loss_color = health_color(thresholds.loss(stats.loss));
+    let mut spans = vec![
+        Span::raw(if collapsed { "▸ " } else { "▾ " }),
+        Span::styled(
+            name.unwrap_or("ungrouped").to_string(),
+            selected_style(Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD), selected),
+        ),
+        Span::raw(format!(" ({} targets)  Avg: ", stats.members)),
+        Span::styled(format!("{:.2}ms", stats.avg_rtt), Style::default().fg(Color::Green)),
+        Span::raw("  Max: "),
+        Span::styled(format!("{:.2}ms", stats.max_rtt), Style::default().fg(Color::Green)),
+        Span::raw("  Loss: "),
+        Span::styled(format!("{:.2}%", stats.loss), Style::default().fg(loss_color)),
+    ];
+    if stats.down > 0 {
+        spans.push(Span::styled(format!("  Down: {}", stats.down), Style::default().fg(Color::Red)));
+    }
+    Line::from(spans)
+}
+
+// the color a health is shown in
+pub fn health_color(health: Health) -> Color {
+    match health {
+        Health::Ok => Color::Green,
+        Health::Warn => Color::Yellow,
+        Health::Crit => Color::Red,
     }
 }
 
-pub fn calculate_jitter(rtt: &VecDeque<f64>) -> f64 {
-    if rtt.len() > 1 {
-        let diffs: Vec<f64> = rtt.iter().zip(rtt.iter().skip(1)).map(|(y1, y2)| (y2 - y1).abs()).collect();
-        let sum: f64 = diffs.iter().sum();
-        sum / diffs.len() as f64
+// the color a target state is shown in
+pub fn status_color(status: Status) -> Color {
+    match status {
+        Status::Unknown => Color::DarkGray,
+        Status::Up => Color::Green,
+        Status::Degraded => Color::Yellow,
+        Status::Down => Color::Red,
+    }
+}
+
+// the probed ip, or the resolve state while there is none yet
+pub fn display_ip(data: &IpData) -> String {
+    let state = if !data.ip.is_empty() {
+        return data.ip.clone();
+    } else if data.unresolved {
+        "unresolved"
     } else {
-        0.0
+        "resolving…"
+    };
+    match data.family {
+        Some(family) => format!("ip{} {}", family, state),
+        None => state.to_string(),
     }
 }
 
-pub fn calculate_loss_pkg(timeout: usize, received: usize) -> f64 {
-    if timeout > 0 {
-        (timeout as f64 / (received as f64 + timeout as f64)) * 100.0
+// highlight the style of the selected target
+pub fn selected_style(style: Style, selected: bool) -> Style {
+    if selected {
+        style.add_modifier(Modifier::REVERSED)
     } else {
-        0.0
+        style
     }
 }
 
-pub fn draw_errors_section<B: Backend>(
+// one line of the error list: time, kind, target and message
+pub fn error_line(record: &ErrorRecord) -> Line<'_> {
+    Line::from(vec![
+        Span::styled("⚠ ", Style::default().fg(Color::Yellow)),
+        Span::raw(format!("{} ", record.time.format("%H:%M:%S"))),
+        Span::styled(format!("[{}] ", record.kind), Style::default().fg(Color::Yellow)),
+        Span::styled(format!("{}: ", record.target), Style::default().fg(Color::Cyan)),
+        Span::styled(&record.message, Style::default().fg(Color::Red)),
+    ])
+}
+
+// marker shown next to a target that has recent errors
+pub fn error_marker(errs: &ErrorLog, id: usize) -> Option<Span<'static>> {
+    match errs.count_for(id) {
+        0 => None,
+        n => Some(Span::styled(format!(" ⚠{}", n), Style::default().fg(Color::Yellow))),
+    }
+}
+
+pub fn draw_errors_section(
     f: &mut Frame,
-    errs: &[String],
+    errs: &ErrorLog,
     area: Rect,
 ) {
     let recent_errors: Vec<Line> = errs
-        .iter()
-        .rev()
-        .take(5)
-        .map(|err| {
-            Line::from(vec![
-                Span::styled("⚠ ", Style::default().fg(Color::Yellow)),
-                Span::styled(err, Style::default().fg(Color::Red))
-            ])
-        })
+        .recent()
+        .take(area.height.saturating_sub(2) as usize)
+        .map(error_line)
         .collect();
 
     if errs.is_empty() {
@@ -61,9 +138,9 @@ pub fn draw_errors_section<B: Backend>(
     } else {
         let errors_paragraph = Paragraph::new(recent_errors)
             .block(Block::default()
-                .title("🚨Recent Errors:")
+                .title("🚨Recent Errors (press e for the full error log):")
                 .borders(Borders::ALL))
             .wrap(Wrap { trim: true });
         f.render_widget(errors_paragraph, area);
     }
-}
\ No newline at end of file
+}
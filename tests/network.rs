@@ -0,0 +1,91 @@
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use chrono::Local;
+use nping::error_log::{ErrorKind, ErrorLog};
+use nping::health::Thresholds;
+use nping::ip_data::{apply_event, IpData};
+use nping::network::{AddressPick, IpFamily, PingContext, PingTaskManager, ResolveOptions, RESOLVE_RETRY_INTERVAL};
+use nping::pinger::MockProber;
+use nping::status::{EventLog, Status};
+use nping::target::TargetSpec;
+
+fn context(resolve_every: Option<Duration>) -> PingContext {
+    let (tx, _rx) = mpsc::sync_channel(0);
+    PingContext {
+        count: 1,
+        interval: 1,
+        running: Arc::new(Mutex::new(true)),
+        paused: Arc::new(Mutex::new(false)),
+        resolve: ResolveOptions {
+            family: IpFamily::V4,
+            dual_stack: false,
+            timeout: Duration::from_secs(1),
+            dns_server: None,
+            pick: AddressPick::First,
+        },
+        resolve_every,
+        errs: Arc::new(Mutex::new(ErrorLog::default())),
+        ping_update_tx: Arc::new(tx),
+    }
+}
+
+#[test]
+fn unresolved_targets_are_retried_soon() {
+    assert_eq!(context(None).resolve_retry_interval(), RESOLVE_RETRY_INTERVAL);
+    // a long re-resolve interval does not hold back the retries
+    assert_eq!(context(Some(Duration::from_secs(3600))).resolve_retry_interval(), RESOLVE_RETRY_INTERVAL);
+    assert_eq!(context(Some(Duration::from_secs(1))).resolve_retry_interval(), Duration::from_secs(1));
+}
+
+#[test]
+fn a_target_without_a_prober_stops_its_task() {
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let ctx = context(None);
+    let errs = ctx.errs.clone();
+    let mut tasks = PingTaskManager::new(runtime.handle().clone(), ctx);
+    // the settings of the simulated target are invalid, so no prober can be made for it
+    tasks.spawn(0, &TargetSpec::new("sim://lat=soon"), "sim".to_string());
+    while !tasks.all_finished() {
+        std::thread::sleep(Duration::from_millis(10));
+    }
+
+    let errs = errs.lock().unwrap();
+    let err = errs.for_target(0).next().unwrap();
+    assert_eq!(err.kind, ErrorKind::Ping);
+    assert_eq!(err.message, "sim ping err: invalid duration: soon");
+}
+
+#[test]
+fn a_target_that_stops_replying_is_probed_until_it_is_back_up() {
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let (tx, rx) = mpsc::sync_channel(0);
+    let mut ctx = context(None);
+    ctx.ping_update_tx = Arc::new(tx);
+    // more lost probes in a row than the probe stream of a single host stops after
+    let replies: Vec<_> = [Some(Duration::from_millis(5)); 2]
+        .into_iter()
+        .chain([None; 15])
+        .chain([Some(Duration::from_millis(5)); 3])
+        .collect();
+    ctx.count = replies.len();
+    let errs = ctx.errs.clone();
+    let mut tasks = PingTaskManager::new(runtime.handle().clone(), ctx);
+    let prober = MockProber::new("10.0.0.1", replies.clone());
+    tasks.spawn_with(0, &TargetSpec::new("10.0.0.1"), "10.0.0.1".to_string(), Box::new(move |_| Ok(Box::new(prober.clone()))));
+
+    // the loss of the outage is not held against the target once it is back
+    let thresholds = Thresholds { warn_loss: 100.0, crit_loss: 100.0, ..Thresholds::default() };
+    let mut ip_data = vec![IpData::new(0, "10.0.0.1".to_string(), "10.0.0.1".to_string())];
+    let mut events = EventLog::default();
+    for _ in &replies {
+        let event = rx.recv_timeout(Duration::from_secs(5)).unwrap();
+        apply_event(&mut ip_data, event, 10, &thresholds, &mut events, Local::now());
+    }
+
+    assert_eq!(ip_data[0].status.status, Status::Up);
+    assert_eq!(ip_data[0].status.outages, 1);
+    let changes: Vec<_> = events.recent().map(|event| (event.from, event.to)).collect();
+    assert_eq!(changes, [(Status::Down, Status::Up), (Status::Up, Status::Down)]);
+    assert!(errs.lock().unwrap().is_empty());
+}
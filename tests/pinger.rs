@@ -0,0 +1,50 @@
+use std::time::Duration;
+use nping::pinger::{ping_with, MockProber, PingOptions, PingResult};
+
+fn options(target: &str) -> PingOptions {
+    PingOptions::new(target, Duration::from_millis(1), None)
+}
+
+#[tokio::test]
+async fn streams_the_scripted_replies_in_order() {
+    let replies = [Some(Duration::from_millis(10)), None, Some(Duration::from_millis(30))];
+    let mut rx = ping_with(options("10.0.0.1"), MockProber::new("10.0.0.1", replies));
+
+    let mut results = Vec::new();
+    for _ in 0..4 {
+        results.push(rx.recv().await.unwrap());
+    }
+    assert!(matches!(&results[0], PingResult::Pong(rtt, target) if *rtt == Duration::from_millis(10) && target == "10.0.0.1"));
+    assert!(matches!(&results[1], PingResult::Timeout(target) if target == "10.0.0.1"));
+    assert!(matches!(&results[2], PingResult::Pong(rtt, _) if *rtt == Duration::from_millis(30)));
+    // the script starts over
+    assert!(matches!(&results[3], PingResult::Pong(rtt, _) if *rtt == Duration::from_millis(10)));
+}
+
+#[tokio::test]
+async fn stops_after_too_many_lost_probes() {
+    let mut options = options("10.0.0.2");
+    options.stop_after_lost_n = 3;
+    let mut rx = ping_with(options, MockProber::new("10.0.0.2", [None]));
+
+    let mut timeouts = 0;
+    while let Some(result) = rx.recv().await {
+        assert!(matches!(result, PingResult::Timeout(_)));
+        timeouts += 1;
+    }
+    assert_eq!(timeouts, 3);
+}
+
+#[tokio::test]
+async fn a_reply_resets_the_lost_count() {
+    let mut options = options("10.0.0.3");
+    options.stop_after_lost_n = 2;
+    let replies = [None, Some(Duration::from_millis(5)), None, None];
+    let mut rx = ping_with(options, MockProber::new("10.0.0.3", replies));
+
+    let mut received = 0;
+    while rx.recv().await.is_some() {
+        received += 1;
+    }
+    assert_eq!(received, 4);
+}
@@ -0,0 +1,111 @@
+use std::time::Duration;
+use nping::pinger::{PingOptions, PingResult, Prober};
+use nping::probe::{prober_for, ProbeKind, SimOptions, SimProber};
+use nping::target::{read_target_file, TargetOptions};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, UdpSocket};
+
+fn options(ip: &str) -> PingOptions {
+    PingOptions::new(ip, Duration::from_millis(100), None)
+}
+
+fn target(probe: ProbeKind, port: Option<u16>) -> TargetOptions {
+    TargetOptions {
+        probe: Some(probe),
+        port,
+        ..TargetOptions::default()
+    }
+}
+
+#[tokio::test]
+async fn tcp_probes_connect_to_the_port() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let mut prober = prober_for(&target(ProbeKind::Tcp, Some(port)), "localhost", &options("127.0.0.1")).unwrap();
+    assert!(matches!(prober.probe(1).await, PingResult::Pong(..)));
+
+    // nothing listens any more, the refused connection is an error
+    drop(listener);
+    assert!(matches!(prober.probe(2).await, PingResult::Unknown(_)));
+}
+
+#[tokio::test]
+async fn udp_probes_wait_for_an_answer() {
+    let server = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+    let port = server.local_addr().unwrap().port();
+    tokio::spawn(async move {
+        let mut buf = [0; 512];
+        while let Ok((read, from)) = server.recv_from(&mut buf).await {
+            server.send_to(&buf[..read], from).await.unwrap();
+        }
+    });
+    // a dns query echoed back carries the id of the query
+    for kind in [ProbeKind::Udp, ProbeKind::Dns] {
+        let mut prober = prober_for(&target(kind, Some(port)), "localhost", &options("127.0.0.1")).unwrap();
+        assert!(matches!(prober.probe(7).await, PingResult::Pong(..)), "{}", kind);
+    }
+}
+
+#[tokio::test]
+async fn http_probes_wait_for_the_status_line() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    tokio::spawn(async move {
+        while let Ok((mut stream, _)) = listener.accept().await {
+            let mut buf = [0; 512];
+            let read = stream.read(&mut buf).await.unwrap();
+            // the host header carries the port when it is not 80
+            let answer: &[u8] = if buf[..read].starts_with(b"GET / HTTP/1.1\r\nHost: localhost:") {
+                b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n"
+            } else {
+                b"SSH-2.0-OpenSSH\r\n"
+            };
+            stream.write_all(answer).await.unwrap();
+        }
+    });
+    let mut prober = prober_for(&target(ProbeKind::Http, Some(port)), "localhost", &options("127.0.0.1")).unwrap();
+    assert!(matches!(prober.probe(1).await, PingResult::Pong(..)));
+
+    // a port that answers something else than http is an error
+    let mut prober = prober_for(&target(ProbeKind::Http, Some(port)), "other", &options("127.0.0.1")).unwrap();
+    assert!(matches!(prober.probe(2).await, PingResult::Unknown(_)));
+}
+
+#[tokio::test]
+async fn sim_probes_stay_within_the_jitter() {
+    let sim = SimOptions {
+        latency: Duration::from_millis(10),
+        jitter: Duration::from_millis(2),
+        loss: 0.0,
+        ..SimOptions::default()
+    };
+    let mut prober = SimProber::new("demo", sim);
+    for seq in 0..5 {
+        match prober.probe(seq).await {
+            PingResult::Pong(rtt, _) => assert!(rtt >= Duration::from_millis(8) && rtt <= Duration::from_millis(12)),
+            result => panic!("unexpected {:?}", result),
+        }
+    }
+
+    let mut prober = SimProber::new("demo", SimOptions { loss: 100.0, ..sim });
+    assert!(matches!(prober.probe(0).await, PingResult::Timeout(_)));
+}
+
+#[test]
+fn target_files_select_the_probe() {
+    let path = std::env::temp_dir().join(format!("nping-probe-test-{}.txt", std::process::id()));
+    let read = |content: &str| {
+        std::fs::write(&path, content).unwrap();
+        read_target_file(&path)
+    };
+
+    let targets = read("web 10.0.0.1 probe=http\nssh 10.0.0.2 probe=tcp port=22\n10.0.0.3\n").unwrap();
+    assert_eq!(targets[0].options.probe, Some(ProbeKind::Http));
+    assert_eq!(targets[1].options.port, Some(22));
+    assert_eq!(targets[2].options.probe, None);
+
+    assert!(read("10.0.0.1 probe=tcp\n").unwrap_err().contains("tcp probes need a port"));
+    assert!(read("10.0.0.1 port=22\n").unwrap_err().contains("no effect on icmp"));
+    assert!(read("10.0.0.1 probe=smtp\n").unwrap_err().contains("unsupported probe type"));
+    let _ = std::fs::remove_file(&path);
+}
@@ -0,0 +1,111 @@
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use nping::error_log::ErrorLog;
+use nping::network::{AddressPick, IpFamily, PingContext, PingEvent, PingTaskManager, ResolveOptions};
+use nping::pinger::{ping_with, PingOptions, PingResult, Prober};
+use nping::probe::{probe_kind, ProbeKind, SimOptions, SimProber};
+use nping::target::{expand_targets, TargetSpec};
+use tokio::runtime::Handle;
+
+#[test]
+fn parses_sim_settings() {
+    let sim: SimOptions = "lat=50ms,jitter=0,loss=2.5%,seed=7".parse().unwrap();
+    assert_eq!(sim.latency, Duration::from_millis(50));
+    assert_eq!(sim.jitter, Duration::ZERO);
+    assert_eq!(sim.loss, 2.5);
+    assert_eq!(sim.seed, Some(7));
+
+    // anything left out keeps its default
+    assert_eq!("".parse::<SimOptions>().unwrap(), SimOptions::default());
+    assert_eq!("latency=1s".parse::<SimOptions>().unwrap().jitter, SimOptions::default().jitter);
+
+    assert!("lat=fast".parse::<SimOptions>().is_err());
+    assert!("loss=120%".parse::<SimOptions>().is_err());
+    assert!("rtt=5ms".parse::<SimOptions>().unwrap_err().contains("unknown sim setting"));
+    assert!("lat".parse::<SimOptions>().is_err());
+}
+
+#[test]
+fn sim_targets_are_single_rows() {
+    let target = TargetSpec::parse("lab:sim://lat=5ms,loss=10%");
+    assert_eq!(target.group.as_deref(), Some("lab"));
+    assert_eq!(target.host, "sim://lat=5ms,loss=10%");
+    assert_eq!(probe_kind(&target.options, &target.host), ProbeKind::Sim);
+
+    let plain = TargetSpec::parse("sim://lat=5ms");
+    assert_eq!(plain.group, None);
+    assert_eq!(expand_targets(vec![plain.clone()]).unwrap(), vec![plain]);
+    assert!(expand_targets(vec![TargetSpec::parse("sim://lat=5")]).is_ok());
+    assert!(expand_targets(vec![TargetSpec::parse("sim://jitter=-1ms")]).is_err());
+}
+
+#[tokio::test]
+async fn a_seed_repeats_the_same_probes() {
+    let sim = SimOptions {
+        latency: Duration::from_millis(2),
+        jitter: Duration::from_millis(1),
+        loss: 30.0,
+        seed: Some(42),
+    };
+    let mut first = SimProber::new("a", sim);
+    let mut second = SimProber::new("b", sim);
+    let mut lost = 0;
+    for seq in 0..20 {
+        match (first.probe(seq).await, second.probe(seq).await) {
+            (PingResult::Pong(a, _), PingResult::Pong(b, _)) => assert_eq!(a, b),
+            (PingResult::Timeout(_), PingResult::Timeout(_)) => lost += 1,
+            results => panic!("different results: {:?}", results),
+        }
+    }
+    assert!(lost > 0 && lost < 20);
+}
+
+#[tokio::test]
+async fn sim_results_come_through_the_ping_stream() {
+    let sim = "lat=1ms,jitter=0ms,loss=100%".parse().unwrap();
+    let mut options = PingOptions::new("sim", Duration::from_millis(1), None);
+    options.stop_after_lost_n = 3;
+    let mut rx = ping_with(options, SimProber::new("sim", sim));
+    let mut timeouts = 0;
+    while let Some(result) = rx.recv().await {
+        assert!(matches!(result, PingResult::Timeout(_)));
+        timeouts += 1;
+    }
+    assert_eq!(timeouts, 3);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn ping_tasks_probe_sim_targets_without_resolving() {
+    let (tx, rx) = mpsc::sync_channel(0);
+    let ctx = PingContext {
+        count: 3,
+        interval: 1,
+        running: Arc::new(Mutex::new(true)),
+        paused: Arc::new(Mutex::new(false)),
+        resolve: ResolveOptions {
+            family: IpFamily::V4,
+            dual_stack: true,
+            timeout: Duration::from_secs(1),
+            dns_server: None,
+            pick: AddressPick::First,
+        },
+        resolve_every: None,
+        errs: Arc::new(Mutex::new(ErrorLog::default())),
+        ping_update_tx: Arc::new(tx),
+    };
+    let target = TargetSpec::parse("sim://lat=1ms,jitter=0ms");
+    // dual stack gives a hostname two rows, a simulated target only one
+    assert_eq!(ctx.resolve.families(&target.host), vec![IpFamily::V4]);
+
+    let mut manager = PingTaskManager::new(Handle::current(), ctx.clone());
+    let id = manager.next_id();
+    manager.spawn_host(id, &target, IpFamily::V4);
+
+    let events = tokio::task::spawn_blocking(move || rx.iter().take(4).collect::<Vec<_>>()).await.unwrap();
+    assert!(matches!(&events[0], PingEvent::Resolved { id: 1, ip, .. } if ip == "sim"));
+    for event in &events[1..] {
+        assert!(matches!(event, PingEvent::Pong { id: 1, rtt } if *rtt == 1.0));
+    }
+    assert!(ctx.errs.lock().unwrap().is_empty());
+}
@@ -0,0 +1,118 @@
+use std::collections::VecDeque;
+use chrono::Local;
+use nping::health::Thresholds;
+use nping::ip_data::{apply_event, IpData};
+use nping::network::{IpFamily, PingEvent};
+use nping::probe::ProbeKind;
+use nping::stats::{calculate_avg_rtt, calculate_jitter, calculate_loss_pkg, calculate_percentile};
+use nping::status::{EventLog, Status};
+use nping::target::TargetSpec;
+
+#[test]
+fn statistics_skip_timeouts() {
+    let rtts = VecDeque::from([10.0, -1.0, 20.0, 30.0]);
+    assert_eq!(calculate_avg_rtt(&rtts), 20.0);
+    assert_eq!(calculate_percentile(&rtts, 50.0), 20.0);
+    assert_eq!(calculate_percentile(&rtts, 95.0), 30.0);
+    assert_eq!(calculate_loss_pkg(1, 3), 25.0);
+}
+
+#[test]
+fn statistics_of_no_probes_are_zero() {
+    let rtts = VecDeque::new();
+    assert_eq!(calculate_avg_rtt(&rtts), 0.0);
+    assert_eq!(calculate_jitter(&rtts), 0.0);
+    assert_eq!(calculate_percentile(&rtts, 95.0), 0.0);
+    assert_eq!(calculate_loss_pkg(0, 0), 0.0);
+}
+
+#[test]
+fn jitter_is_the_mean_difference() {
+    let rtts = VecDeque::from([10.0, 20.0, 15.0]);
+    assert_eq!(calculate_jitter(&rtts), 7.5);
+}
+
+#[test]
+fn ip_data_accumulates_replies_and_timeouts() {
+    let now = Local::now();
+    let mut data = IpData::new(1, "example".to_string(), "10.0.0.1".to_string());
+    data.update_rtt(12.0, 3, now);
+    data.update_rtt(8.0, 3, now);
+    data.update_timeout(3, now);
+    data.update_rtt(20.0, 3, now);
+
+    assert_eq!(data.received, 3);
+    assert_eq!(data.timeout, 1);
+    assert_eq!(data.min_rtt, 8.0);
+    assert_eq!(data.max_rtt, 20.0);
+    // only the last point_num rtts are kept, the history keeps every probe
+    assert_eq!(data.rtts, VecDeque::from([8.0, -1.0, 20.0]));
+    assert_eq!(data.pop_count, 1);
+    assert_eq!(data.history.len(), 4);
+}
+
+#[test]
+fn events_update_the_matching_target() {
+    let now = Local::now();
+    let thresholds = Thresholds::default();
+    let mut events = EventLog::default();
+    let mut ip_data = vec![
+        IpData::new(1, "a".to_string(), "10.0.0.1".to_string()),
+        IpData::new(2, "b".to_string(), "10.0.0.2".to_string()),
+    ];
+
+    apply_event(&mut ip_data, PingEvent::Pong { id: 1, rtt: 5.0 }, 10, &thresholds, &mut events, now);
+    for _ in 0..3 {
+        apply_event(&mut ip_data, PingEvent::Timeout { id: 2 }, 10, &thresholds, &mut events, now);
+    }
+
+    assert_eq!(ip_data[0].received, 1);
+    assert_eq!(ip_data[0].status.status, Status::Up);
+    assert_eq!(ip_data[1].timeout, 3);
+    assert_eq!(ip_data[1].status.status, Status::Down);
+    assert_eq!(events.total(), 1);
+}
+
+#[test]
+fn session_statistics_cover_every_probe() {
+    let now = Local::now();
+    let mut data = IpData::new(1, "example".to_string(), "10.0.0.1".to_string());
+    for rtt in [10.0, 30.0, 20.0, 40.0] {
+        data.update_rtt(rtt, 2, now);
+    }
+    data.update_timeout(2, now);
+
+    // the views only see the last two probes
+    assert_eq!(calculate_avg_rtt(&data.rtts), 40.0);
+    assert_eq!(data.session_avg_rtt(), 25.0);
+    // 20 + 10 + 20 between the replies and 41 to the timeout
+    assert_eq!(data.session_jitter(), 91.0 / 4.0);
+
+    data.reset();
+    assert_eq!(data.session_avg_rtt(), 0.0);
+    assert_eq!(data.session_jitter(), 0.0);
+}
+
+#[test]
+fn reset_keeps_the_settings_of_the_target() {
+    let mut target = TargetSpec::parse("lab:example.com");
+    target.label = Some("web".to_string());
+    target.options.warn_rtt = Some(50.0);
+    target.options.probe = Some(ProbeKind::Tcp);
+    target.options.port = Some(443);
+    let mut data = IpData::for_target(1, &target, "10.0.0.1".to_string());
+    data.family = Some(IpFamily::V6);
+    data.addresses = vec!["10.0.0.1".to_string(), "10.0.0.2".to_string()];
+    data.update_rtt(10.0, 10, Local::now());
+    data.update_timeout(10, Local::now());
+
+    data.reset();
+    assert_eq!((data.received, data.timeout), (0, 0));
+    assert!(data.rtts.is_empty() && data.history.is_empty());
+    assert_eq!(data.label.as_deref(), Some("web"));
+    assert_eq!(data.group.as_deref(), Some("lab"));
+    assert_eq!(data.options, target.options);
+    assert_eq!(data.family, Some(IpFamily::V6));
+    assert_eq!(data.addresses, ["10.0.0.1", "10.0.0.2"]);
+    assert_eq!((data.id, data.addr.as_str(), data.ip.as_str()), (1, "example.com", "10.0.0.1"));
+}
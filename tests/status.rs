@@ -0,0 +1,107 @@
+use std::time::Duration;
+use chrono::{DateTime, Local, TimeZone};
+use nping::health::{Health, Thresholds};
+use nping::status::{Status, StatusTracker};
+use nping::target::TargetOptions;
+
+// the time `secs` seconds into the session
+fn at(secs: i64) -> DateTime<Local> {
+    Local.with_ymd_and_hms(2026, 1, 1, 10, 0, 0).unwrap() + chrono::Duration::seconds(secs)
+}
+
+// feed one probe a second from `start`, true for a reply, returning the state changes, the
+// target stays below its thresholds
+fn probes(tracker: &mut StatusTracker, thresholds: &Thresholds, start: i64, replies: &[bool]) -> Vec<(Status, Status)> {
+    let mut changes = Vec::new();
+    for (secs, reply) in (start..).zip(replies) {
+        if let Some((from, _)) = tracker.probe(*reply, Health::Ok, thresholds, at(secs)) {
+            changes.push((from, tracker.status));
+        }
+    }
+    changes
+}
+
+#[test]
+fn goes_down_after_timeouts_in_a_row() {
+    let thresholds = Thresholds::default();
+    let mut tracker = StatusTracker::default();
+    assert!(probes(&mut tracker, &thresholds, 0, &[false, false]).is_empty());
+    assert_eq!(tracker.status, Status::Unknown);
+    assert_eq!(probes(&mut tracker, &thresholds, 2, &[false]), [(Status::Unknown, Status::Down)]);
+    assert_eq!(tracker.outages, 1);
+}
+
+#[test]
+fn comes_up_after_replies_in_a_row() {
+    let thresholds = Thresholds::default();
+    let mut tracker = StatusTracker::default();
+    // coming up for the first time is no event
+    assert!(probes(&mut tracker, &thresholds, 0, &[true]).is_empty());
+    assert_eq!(tracker.status, Status::Up);
+
+    // two timeouts are not an outage
+    assert!(probes(&mut tracker, &thresholds, 1, &[false, false, true]).is_empty());
+    assert_eq!(probes(&mut tracker, &thresholds, 4, &[false, false, false]), [(Status::Up, Status::Down)]);
+
+    // a single reply does not bring it back
+    assert!(probes(&mut tracker, &thresholds, 7, &[true, false, true]).is_empty());
+    assert_eq!(tracker.status, Status::Down);
+    assert_eq!(probes(&mut tracker, &thresholds, 10, &[true]), [(Status::Down, Status::Up)]);
+    assert_eq!(tracker.outages, 1);
+}
+
+#[test]
+fn degraded_is_up_above_a_threshold() {
+    let thresholds = Thresholds::default();
+    let mut tracker = StatusTracker::default();
+    tracker.probe(true, Health::Ok, &thresholds, at(0));
+    assert_eq!(tracker.probe(true, Health::Warn, &thresholds, at(1)).map(|(from, _)| from), Some(Status::Up));
+    assert_eq!(tracker.status, Status::Degraded);
+    assert!(tracker.probe(true, Health::Crit, &thresholds, at(2)).is_none());
+    assert_eq!(tracker.probe(true, Health::Ok, &thresholds, at(3)).map(|(from, _)| from), Some(Status::Degraded));
+    assert_eq!(tracker.status, Status::Up);
+}
+
+#[test]
+fn flaps_when_changing_often() {
+    let thresholds = Thresholds { down_after: 1, up_after: 1, ..Thresholds::default() };
+    let mut tracker = StatusTracker::default();
+    // down, up, down, up within a minute
+    assert_eq!(probes(&mut tracker, &thresholds, 0, &[true, false, true, false, true]).len(), 4);
+    assert!(tracker.flapping);
+
+    // ten minutes later the changes are forgotten
+    assert!(probes(&mut tracker, &thresholds, 700, &[true]).is_empty());
+    assert!(!tracker.flapping);
+}
+
+#[test]
+fn downtime_adds_up_the_outages() {
+    let thresholds = Thresholds::default();
+    let mut tracker = StatusTracker::default();
+    probes(&mut tracker, &thresholds, 0, &[true, false, false, false]);
+    // down since the third timeout at 3s
+    assert_eq!(tracker.downtime(at(13)), Duration::from_secs(10));
+
+    let changes: Vec<_> = (20..22).map(|secs| tracker.probe(true, Health::Ok, &thresholds, at(secs))).collect();
+    assert_eq!(changes[1], Some((Status::Down, Duration::from_secs(18))));
+    assert_eq!(tracker.downtime(at(100)), Duration::from_secs(18));
+
+    probes(&mut tracker, &thresholds, 200, &[false, false, false]);
+    assert_eq!(tracker.downtime(at(212)), Duration::from_secs(28));
+    assert_eq!(tracker.outages, 2);
+}
+
+#[test]
+fn down_and_up_after_can_be_changed() {
+    let thresholds = Thresholds { down_after: 5, up_after: 1, ..Thresholds::default() };
+    let mut tracker = StatusTracker::default();
+    probes(&mut tracker, &thresholds, 0, &[true, false, false, false, false]);
+    assert_eq!(tracker.status, Status::Up);
+    assert_eq!(probes(&mut tracker, &thresholds, 5, &[false, true]), [(Status::Up, Status::Down), (Status::Down, Status::Up)]);
+
+    // a target can set its own
+    let options = TargetOptions { down_after: Some(1), ..TargetOptions::default() };
+    let thresholds = thresholds.for_target(&options);
+    assert_eq!((thresholds.down_after, thresholds.up_after), (1, 1));
+}
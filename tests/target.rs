@@ -0,0 +1,247 @@
+use std::path::PathBuf;
+use std::time::Duration;
+use nping::probe::ProbeKind;
+use nping::target::{expand_target, expand_targets, parse_duration, parse_loss, parse_probes, parse_rtt, read_target_file, TargetOptions, TargetSpec, MAX_EXPANDED_TARGETS};
+
+#[test]
+fn parses_group_prefixes() {
+    let target = TargetSpec::parse("dc-east:10.0.0.1");
+    assert_eq!(target.group.as_deref(), Some("dc-east"));
+    assert_eq!(target.host, "10.0.0.1");
+
+    // ipv6 addresses and urls are not group:host
+    assert_eq!(TargetSpec::parse("2001:db8::1").group, None);
+    assert_eq!(TargetSpec::parse("http://example.com").group, None);
+}
+
+#[test]
+fn expands_subnets_and_ranges() {
+    assert_eq!(expand_target("10.0.0.0/30").unwrap(), ["10.0.0.1", "10.0.0.2"]);
+    assert_eq!(expand_target("10.0.0.10-12").unwrap(), ["10.0.0.10", "10.0.0.11", "10.0.0.12"]);
+    assert_eq!(expand_target("example.com").unwrap(), ["example.com"]);
+    assert!(expand_target("10.0.0.0/8").is_err());
+    assert!(expand_target("10.0.0.12-10").is_err());
+}
+
+#[test]
+fn expanded_targets_are_unique() {
+    let targets = vec![TargetSpec::new("10.0.0.1"), TargetSpec::new("10.0.0.0/30")];
+    let hosts: Vec<String> = expand_targets(targets).unwrap().into_iter().map(|target| target.host).collect();
+    assert_eq!(hosts, ["10.0.0.1", "10.0.0.2"]);
+}
+
+#[test]
+fn parses_durations() {
+    assert_eq!(parse_duration("500ms").unwrap(), Duration::from_millis(500));
+    assert_eq!(parse_duration("2").unwrap(), Duration::from_secs(2));
+    assert_eq!(parse_duration("30s").unwrap(), Duration::from_secs(30));
+    assert_eq!(parse_duration("5m").unwrap(), Duration::from_secs(300));
+    assert_eq!(parse_duration("1h").unwrap(), Duration::from_secs(3600));
+    assert_eq!(parse_duration("1.5s").unwrap(), Duration::from_millis(1500));
+    assert_eq!(parse_duration(" 10s ").unwrap(), Duration::from_secs(10));
+}
+
+#[test]
+fn rejects_invalid_durations() {
+    assert_eq!(parse_duration("0s").unwrap_err(), "duration must be positive: 0s");
+    assert_eq!(parse_duration("5d").unwrap_err(), "invalid duration unit: d");
+    assert_eq!(parse_duration("").unwrap_err(), "invalid duration: ");
+    assert_eq!(parse_duration("ms").unwrap_err(), "invalid duration: ms");
+    assert!(parse_duration("-5s").is_err());
+    assert!(parse_duration("1.2.3s").is_err());
+}
+
+#[test]
+fn parses_thresholds() {
+    assert_eq!(parse_rtt("0.2s").unwrap(), 200.0);
+    assert_eq!(parse_loss("5%").unwrap(), 5.0);
+    assert!(parse_loss("150%").is_err());
+    assert_eq!(parse_probes("3").unwrap(), 3);
+    assert!(parse_probes("0").is_err());
+}
+
+#[test]
+fn targets_set_when_they_go_down_and_up() {
+    let targets = read("down-after.txt", "10.0.0.1 down-after=5 up_after=1\n").unwrap();
+    assert_eq!((targets[0].options.down_after, targets[0].options.up_after), (Some(5), Some(1)));
+    let targets = read("down-after.toml", "[[target]]\nhost = \"10.0.0.1\"\ndown_after = 5\n").unwrap();
+    assert_eq!(targets[0].options.down_after, Some(5));
+    assert!(read("down-after-0.toml", "[[target]]\nhost = \"10.0.0.1\"\nup_after = 0\n").is_err());
+}
+
+#[test]
+fn expands_edge_cases_of_subnets_and_ranges() {
+    // a /31 and /32 have no network and broadcast address to skip
+    assert_eq!(expand_target("10.0.0.8/31").unwrap(), ["10.0.0.8", "10.0.0.9"]);
+    assert_eq!(expand_target("10.0.0.8/32").unwrap(), ["10.0.0.8"]);
+    // the host bits of the address are ignored
+    assert_eq!(expand_target("10.0.0.5/30").unwrap(), ["10.0.0.5", "10.0.0.6"]);
+    assert_eq!(expand_target("2001:db8::/126").unwrap(), ["2001:db8::", "2001:db8::1", "2001:db8::2", "2001:db8::3"]);
+
+    // a full end address may cross octets
+    assert_eq!(expand_target("10.0.0.254-10.0.1.1").unwrap(), ["10.0.0.254", "10.0.0.255", "10.0.1.0", "10.0.1.1"]);
+    assert_eq!(expand_target("2001:db8::1-2001:db8::2").unwrap(), ["2001:db8::1", "2001:db8::2"]);
+    assert_eq!(expand_target("10.0.0.7-7").unwrap(), ["10.0.0.7"]);
+
+    // hostnames with a dash or a slash are not ranges or subnets
+    assert_eq!(expand_target("my-host").unwrap(), ["my-host"]);
+    assert_eq!(expand_target("example.com/path").unwrap(), ["example.com/path"]);
+}
+
+#[test]
+fn rejects_invalid_subnets_and_ranges() {
+    assert_eq!(expand_target("10.0.0.0/33").unwrap_err(), "invalid prefix length in 10.0.0.0/33");
+    assert_eq!(expand_target("10.0.0.0/x").unwrap_err(), "invalid prefix length in 10.0.0.0/x");
+    assert_eq!(expand_target("10.0.0.1-256").unwrap_err(), "invalid end of range in 10.0.0.1-256");
+    assert_eq!(expand_target("2001:db8::1-5").unwrap_err(), "invalid end of range in 2001:db8::1-5");
+    assert_eq!(expand_target("10.0.0.1-2001:db8::1").unwrap_err(), "invalid end of range in 10.0.0.1-2001:db8::1");
+    assert_eq!(expand_target("10.0.0.12-10").unwrap_err(), "range 10.0.0.12-10 ends before it starts");
+}
+
+#[test]
+fn limits_the_number_of_expanded_targets() {
+    // a /22 has 1022 hosts, a /21 more than the limit
+    assert_eq!(expand_target("10.0.0.0/22").unwrap().len(), 1022);
+    assert_eq!(expand_target("10.0.0.0/21").unwrap_err(), format!("10.0.0.0/21 expands to more than {} targets", MAX_EXPANDED_TARGETS));
+    assert_eq!(expand_target("10.0.0.0-10.0.3.255").unwrap().len(), MAX_EXPANDED_TARGETS);
+    assert!(expand_target("10.0.0.0-10.0.4.0").is_err());
+    assert!(expand_target("2001:db8::/64").is_err());
+}
+
+#[test]
+fn expanded_targets_keep_their_group_and_options() {
+    let mut target = TargetSpec::parse("lab:10.0.0.0/30");
+    target.label = Some("subnet".to_string());
+    target.options.interval = Some(Duration::from_secs(2));
+    let expanded = target.expand().unwrap();
+    assert_eq!(expanded.len(), 2);
+    for target in &expanded {
+        assert_eq!(target.group.as_deref(), Some("lab"));
+        assert_eq!(target.options.interval, Some(Duration::from_secs(2)));
+        // a label names a single target, not every address of the subnet
+        assert_eq!(target.label, None);
+    }
+}
+
+// a target file with the content in the temp dir, the name keeps tests running at once apart
+fn target_file(name: &str, content: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("nping-{}-{}", std::process::id(), name));
+    std::fs::write(&path, content).unwrap();
+    path
+}
+
+fn read(name: &str, content: &str) -> Result<Vec<TargetSpec>, String> {
+    let path = target_file(name, content);
+    let targets = read_target_file(&path);
+    std::fs::remove_file(path).unwrap();
+    targets
+}
+
+#[test]
+fn reads_the_line_format() {
+    let targets = read("lines.txt", "\
+# comments and blank lines are skipped
+
+10.0.0.1
+core-router 10.0.0.2   # a label and a host
+[dc-east]
+web 10.0.0.3 interval=2s warn-rtt=50ms crit_loss=20%
+api 10.0.0.4 probe=tcp port=443
+[ ]
+10.0.0.5
+").unwrap();
+
+    let hosts: Vec<&str> = targets.iter().map(|target| target.host.as_str()).collect();
+    assert_eq!(hosts, ["10.0.0.1", "10.0.0.2", "10.0.0.3", "10.0.0.4", "10.0.0.5"]);
+    assert_eq!(targets[0], TargetSpec::new("10.0.0.1"));
+    assert_eq!(targets[1].label.as_deref(), Some("core-router"));
+    assert_eq!(targets[1].group, None);
+    assert_eq!(targets[2].group.as_deref(), Some("dc-east"));
+    assert_eq!(targets[2].options, TargetOptions {
+        interval: Some(Duration::from_secs(2)),
+        warn_rtt: Some(50.0),
+        crit_loss: Some(20.0),
+        ..TargetOptions::default()
+    });
+    assert_eq!(targets[3].options.probe, Some(ProbeKind::Tcp));
+    assert_eq!(targets[3].options.port, Some(443));
+    // an empty section ends the group
+    assert_eq!(targets[4].group, None);
+}
+
+#[test]
+fn reads_simulated_targets_with_settings() {
+    let targets = read("sim.txt", "sim://lat=20ms,jitter=0ms label=x\nsim-a sim://loss=2% interval=2s\n").unwrap();
+    assert_eq!(targets[0].host, "sim://lat=20ms,jitter=0ms");
+    assert_eq!(targets[0].label.as_deref(), Some("x"));
+    assert_eq!(targets[1].host, "sim://loss=2%");
+    assert_eq!(targets[1].label.as_deref(), Some("sim-a"));
+    assert_eq!(targets[1].options.interval, Some(Duration::from_secs(2)));
+}
+
+#[test]
+fn reports_the_line_of_an_invalid_target() {
+    let err = read("bad-option.txt", "10.0.0.1\n10.0.0.2 color=red\n").unwrap_err();
+    assert!(err.ends_with("line 2: unknown target option: color"), "{}", err);
+    let err = read("bad-words.txt", "a b 10.0.0.1\n").unwrap_err();
+    assert!(err.ends_with("line 1: expected a host or a label and a host, got: a b 10.0.0.1"), "{}", err);
+    let err = read("missing-host.txt", "interval=1s\n").unwrap_err();
+    assert!(err.ends_with("line 1: missing host"), "{}", err);
+    let err = read("missing-port.txt", "10.0.0.1 probe=udp\n").unwrap_err();
+    assert!(err.ends_with("line 1: udp probes need a port"), "{}", err);
+    let err = read("two-labels.txt", "web 10.0.0.1 label=api\n").unwrap_err();
+    assert!(err.ends_with("line 1: the label is given twice"), "{}", err);
+    let err = read("useless-port.txt", "10.0.0.1 port=80\n").unwrap_err();
+    assert!(err.ends_with("line 1: port has no effect on icmp probes"), "{}", err);
+}
+
+#[test]
+fn reads_the_toml_format() {
+    let targets = read("targets.toml", r#"
+[[target]]
+host = "10.0.0.1"
+
+[[target]]
+host = "10.0.0.2"
+label = "web"
+group = "dc-east"
+probe = "http"
+interval = "500ms"
+warn_rtt = "100ms"
+crit_rtt = "0.3s"
+warn_loss = "1%"
+crit_loss = "20"
+"#).unwrap();
+
+    assert_eq!(targets[0], TargetSpec::new("10.0.0.1"));
+    assert_eq!(targets[1], TargetSpec {
+        host: "10.0.0.2".to_string(),
+        label: Some("web".to_string()),
+        group: Some("dc-east".to_string()),
+        options: TargetOptions {
+            probe: Some(ProbeKind::Http),
+            port: None,
+            interval: Some(Duration::from_millis(500)),
+            warn_rtt: Some(100.0),
+            crit_rtt: Some(300.0),
+            warn_loss: Some(1.0),
+            crit_loss: Some(20.0),
+            down_after: None,
+            up_after: None,
+        },
+    });
+}
+
+#[test]
+fn rejects_invalid_toml_targets() {
+    let err = read("unknown-key.toml", "[[target]]\nhost = \"a\"\ncolor = \"red\"\n").unwrap_err();
+    assert!(err.contains("unknown field `color`"), "{}", err);
+    let err = read("no-host.toml", "[[target]]\nlabel = \"a\"\n").unwrap_err();
+    assert!(err.contains("missing field `host`"), "{}", err);
+    let err = read("bad-interval.toml", "[[target]]\nhost = \"a\"\ninterval = \"soon\"\n").unwrap_err();
+    assert!(err.ends_with("invalid duration: soon"), "{}", err);
+    let err = read("missing-port.toml", "[[target]]\nhost = \"a\"\nprobe = \"tcp\"\n").unwrap_err();
+    assert!(err.ends_with("a: tcp probes need a port"), "{}", err);
+    // the error names the file
+    assert!(err.contains("missing-port.toml: "), "{}", err);
+}